@@ -0,0 +1,236 @@
+//! Hands out renet connect tokens so the server can run in secure mode.
+//! Clients send a `TokenRequest` over tcp and get a `TokenResponse` back,
+//! the server trusts the account id and name in the token's user data.
+
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use bevy_renet::renet::{generate_random_bytes, ConnectToken, NETCODE_KEY_BYTES};
+use lib::{
+    auth::{key_to_hex, TokenRequest, TokenResponse, UserData},
+    config::NetConfig,
+};
+use serde::{Deserialize, Serialize};
+
+/// How long a client has to use its token.
+const TOKEN_EXPIRE_SECONDS: u64 = 30;
+/// Seconds without packets before the connection made with the token drops.
+const TOKEN_TIMEOUT_SECONDS: i32 = 15;
+/// Longest name the user data has room for.
+const MAX_NAME_LEN: usize = 32;
+const MAX_PASSWORD_LEN: usize = 64;
+/// Requests are a name and a password, anything longer is refused unread.
+const MAX_REQUEST_BYTES: usize = 256;
+/// How long a client has to send its whole request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections served at the same time, more are turned away.
+const MAX_CONNECTIONS: usize = 64;
+/// Read from the working directory, written on every registration.
+const ACCOUNTS_FILE: &str = "accounts.ron";
+
+/// `password_hash` is an argon2 PHC string.
+#[derive(Clone, Serialize, Deserialize)]
+struct Account {
+    id: u64,
+    password_hash: String,
+}
+
+/// Accounts by name, the first login with a name registers it with the
+/// password it came with.
+#[derive(Default, Serialize, Deserialize)]
+struct Accounts {
+    accounts: HashMap<String, Account>,
+    next: u64,
+}
+
+impl Accounts {
+    fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let ron = std::fs::read_to_string(path)
+            .map_err(|err| format!("reading {}: {err}", path.display()))?;
+        ron::from_str(&ron).map_err(|err| format!("parsing {}: {err}", path.display()))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let ron = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|err| format!("writing accounts: {err}"))?;
+        std::fs::write(path, ron).map_err(|err| format!("writing {}: {err}", path.display()))
+    }
+}
+
+/// The account id of `name` if `password` is its password, registering
+/// the name when nobody has it yet. Hashing is slow on purpose, so it
+/// happens outside the lock.
+fn login(accounts: &Mutex<Accounts>, name: &str, password: &str) -> Result<u64, String> {
+    let argon2 = Argon2::default();
+    let account = accounts.lock().unwrap().accounts.get(name).cloned();
+    if let Some(account) = account {
+        let hash = PasswordHash::new(&account.password_hash).map_err(|err| err.to_string())?;
+        argon2
+            .verify_password(password.as_bytes(), &hash)
+            .map_err(|_| format!("wrong password for {name}"))?;
+        return Ok(account.id);
+    }
+    let salt =
+        SaltString::encode_b64(&generate_random_bytes::<16>()).map_err(|err| err.to_string())?;
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|err| err.to_string())?
+        .to_string();
+    let mut accounts = accounts.lock().unwrap();
+    if accounts.accounts.contains_key(name) {
+        return Err(format!("{name} was registered just now"));
+    }
+    accounts.next += 1;
+    let id = accounts.next;
+    accounts
+        .accounts
+        .insert(name.to_string(), Account { id, password_hash });
+    accounts.save(Path::new(ACCOUNTS_FILE))?;
+    println!("registered account {id} {name}");
+    Ok(id)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--gen-key") {
+        let key: [u8; NETCODE_KEY_BYTES] = generate_random_bytes();
+        println!("{}", key_to_hex(&key));
+        return;
+    }
+    let config = NetConfig::from_args(args).unwrap_or_else(|err| {
+        eprintln!("{err}\n    --gen-key              print a new private key");
+        std::process::exit(2);
+    });
+    let private_key = config.private_key().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+    let accounts = Accounts::load(Path::new(ACCOUNTS_FILE)).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+    let accounts = Arc::new(Mutex::new(accounts));
+    let auth_addr = SocketAddr::new(config.bind_addr, config.auth_port);
+    let listener = TcpListener::bind(auth_addr).unwrap();
+    println!("auth listening on {auth_addr}");
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            let response: TokenResponse = Err("auth is busy, try again".to_string());
+            let _ = stream.write_all(&bincode::serialize(&response).unwrap());
+            continue;
+        }
+        // a thread each, so a slow client doesn't hold up the others
+        let (config, accounts, connections) =
+            (config.clone(), accounts.clone(), connections.clone());
+        std::thread::spawn(move || {
+            let response = respond(&mut stream, &config, &private_key, &accounts);
+            if let Err(err) = &response {
+                println!("refused token: {err}");
+            }
+            let _ = stream.write_all(&bincode::serialize(&response).unwrap());
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Reads until the client shuts down its side, giving up after
+/// MAX_REQUEST_BYTES or REQUEST_TIMEOUT.
+fn read_request(stream: &mut TcpStream) -> Result<Vec<u8>, String> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut request = vec![];
+    let mut buf = [0; MAX_REQUEST_BYTES];
+    loop {
+        let left = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or("timed out reading the request")?;
+        stream
+            .set_read_timeout(Some(left))
+            .map_err(|err| err.to_string())?;
+        let read = stream.read(&mut buf).map_err(|err| match err.kind() {
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+                "timed out reading the request".to_string()
+            }
+            _ => err.to_string(),
+        })?;
+        if read == 0 {
+            return Ok(request);
+        }
+        request.extend_from_slice(&buf[..read]);
+        if request.len() > MAX_REQUEST_BYTES {
+            return Err(format!("requests are at most {MAX_REQUEST_BYTES} bytes"));
+        }
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    config: &NetConfig,
+    private_key: &[u8; NETCODE_KEY_BYTES],
+    accounts: &Mutex<Accounts>,
+) -> TokenResponse {
+    let request = read_request(stream)?;
+    let request: TokenRequest = bincode::deserialize(&request).map_err(|err| err.to_string())?;
+    if request.protocol_id != config.protocol_id {
+        return Err(format!(
+            "protocol {} doesn't match the server's {}",
+            request.protocol_id, config.protocol_id
+        ));
+    }
+    let name = request.name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("names are 1 to {MAX_NAME_LEN} bytes long"));
+    }
+    if request.password.is_empty() || request.password.len() > MAX_PASSWORD_LEN {
+        return Err(format!("passwords are 1 to {MAX_PASSWORD_LEN} bytes long"));
+    }
+    let user_data = UserData {
+        account_id: login(accounts, name, &request.password)?,
+        name: name.to_string(),
+    };
+    // a new client id every login, the server refuses an account that is
+    // already connected
+    let client_id = u64::from_le_bytes(generate_random_bytes());
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let token = ConnectToken::generate(
+        current_time,
+        config.protocol_id,
+        TOKEN_EXPIRE_SECONDS,
+        client_id,
+        TOKEN_TIMEOUT_SECONDS,
+        vec![config.public_socket_addr()],
+        Some(&user_data.to_bytes()),
+        private_key,
+    )
+    .map_err(|err| err.to_string())?;
+    println!(
+        "issued token for account {} {} as client {client_id}",
+        user_data.account_id, user_data.name
+    );
+    let mut bytes = vec![];
+    token.write(&mut bytes).map_err(|err| err.to_string())?;
+    Ok(bytes)
+}
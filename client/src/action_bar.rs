@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use lib::{
+    abilities::Abilities,
+    combat::Mana,
+    components::{Action, ControlledEntity, PlayerCommand},
+};
+
+/// The default keys of the action bar slots.
+pub fn action_bar_input_map() -> InputMap<Action> {
+    InputMap::new([
+        (KeyCode::Key1, Action::Bar1),
+        (KeyCode::Key2, Action::Bar2),
+        (KeyCode::Key3, Action::Bar3),
+        (KeyCode::Key4, Action::Bar4),
+        (KeyCode::Key5, Action::Bar5),
+        (KeyCode::Key6, Action::Bar6),
+    ])
+}
+
+/// A slot of the action bar, its text is the key and ability name.
+#[derive(Component, Debug)]
+pub struct ActionBarSlot(pub Action);
+
+/// Uses the ability of every pressed slot on the current Target.
+pub fn use_action_bar(
+    query: Query<&ActionState<Action>, With<ControlledEntity>>,
+    mut player_command: EventWriter<PlayerCommand>,
+) {
+    if let Ok(action_state) = query.get_single() {
+        for action in action_state.get_just_pressed() {
+            player_command.send(PlayerCommand::UseAbility(action.ability(), None));
+        }
+    }
+}
+
+/// Builds the bar from the controlled entity's InputMap, so rebinding a
+/// key relabels its slot. Slots without an ability are left out.
+pub fn spawn_action_bar(
+    mut commands: Commands,
+    input_map: Query<&InputMap<Action>, Added<InputMap<Action>>>,
+    abilities: Res<Abilities>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(input_map) = input_map.get_single() else {
+        return;
+    };
+    let bar = commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                gap: Size::width(Val::Px(16.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id();
+    for action in Action::variants() {
+        let Some(ability) = abilities.get(action.ability()) else {
+            continue;
+        };
+        let keys: Vec<String> = input_map
+            .get(action)
+            .iter()
+            .map(|input| input.to_string().trim_start_matches("Key").to_string())
+            .collect();
+        let slot = commands
+            .spawn((
+                TextBundle::from_section(
+                    format!("{} {}", keys.join("/"), ability.name),
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                ActionBarSlot(action),
+            ))
+            .id();
+        commands.entity(bar).add_child(slot);
+    }
+}
+
+/// Greys out the slots the player doesn't have the mana for.
+pub fn update_action_bar(
+    mana: Query<&Mana, (With<ControlledEntity>, Changed<Mana>)>,
+    mut slots: Query<(&ActionBarSlot, &mut Text)>,
+    abilities: Res<Abilities>,
+) {
+    let Ok(mana) = mana.get_single() else {
+        return;
+    };
+    for (slot, mut text) in slots.iter_mut() {
+        let Some(ability) = abilities.get(slot.0.ability()) else {
+            continue;
+        };
+        text.sections[0].style.color = if ability.cost <= mana.mp {
+            Color::WHITE
+        } else {
+            Color::GRAY
+        };
+    }
+}
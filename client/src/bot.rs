@@ -0,0 +1,223 @@
+//! Headless players for load testing, they connect like the real client
+//! and click and attack through the same ClientNetPlugin.
+
+use std::{str::FromStr, time::Duration};
+
+use bevy::prelude::*;
+use client::{
+    connection::new_renet_client,
+    plugins::{load_data, ClientNetPlugin},
+};
+use lib::{
+    abilities::AbilityId,
+    archetypes::Archetypes,
+    components::{ControlledEntity, EntityType, LeftClick, PlayerCommand, Tile},
+    config::NetConfig,
+    nav::{distance, NavGrid},
+    ClickEvent,
+};
+use rand::{seq::IteratorRandom, Rng};
+
+const USAGE: &str = "bot options:
+    --bots <n>             bots to run, each with its own connection
+    --behavior <b>         wander, hunt, spam or mixed
+    --think-ms <ms>        time between a bot's decisions";
+
+/// How long the loop waits between updating all bots.
+const FRAME: Duration = Duration::from_millis(16);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Behavior {
+    /// Walks to random reachable tiles.
+    Wander,
+    /// Attacks the closest mob.
+    Hunt,
+    /// Spams the auto attack.
+    Spam,
+    /// Any of the above, picked again at every decision.
+    Mixed,
+}
+
+impl Behavior {
+    /// What to do this time, Mixed picks one of the others.
+    fn pick(self, rng: &mut impl Rng) -> Self {
+        match self {
+            Behavior::Mixed => match rng.gen_range(0..3) {
+                0 => Behavior::Wander,
+                1 => Behavior::Hunt,
+                _ => Behavior::Spam,
+            },
+            behavior => behavior,
+        }
+    }
+}
+
+impl FromStr for Behavior {
+    type Err = String;
+
+    fn from_str(behavior: &str) -> Result<Self, Self::Err> {
+        match behavior {
+            "wander" => Ok(Behavior::Wander),
+            "hunt" => Ok(Behavior::Hunt),
+            "spam" => Ok(Behavior::Spam),
+            "mixed" => Ok(Behavior::Mixed),
+            _ => Err(format!("unknown behavior {behavior}\n{USAGE}")),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Bot {
+    pub behavior: Behavior,
+    pub think: Timer,
+}
+
+struct BotOptions {
+    count: usize,
+    behavior: Behavior,
+    think: Duration,
+}
+
+impl BotOptions {
+    /// Takes the bot options out of `args`, the rest is for NetConfig.
+    fn from_args(args: Vec<String>) -> Result<(Self, Vec<String>), String> {
+        let mut options = BotOptions {
+            count: 1,
+            behavior: Behavior::Mixed,
+            think: Duration::from_millis(1000),
+        };
+        let mut rest = vec![];
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let parse = |value: Option<String>| {
+                value.ok_or_else(|| format!("{flag} needs a value\n{USAGE}"))
+            };
+            match flag.as_str() {
+                "--bots" => {
+                    options.count = parse(args.next())?
+                        .parse()
+                        .map_err(|_| format!("--bots needs a number\n{USAGE}"))?
+                }
+                "--behavior" => options.behavior = parse(args.next())?.parse()?,
+                "--think-ms" => {
+                    let ms = parse(args.next())?
+                        .parse()
+                        .map_err(|_| format!("--think-ms needs a number\n{USAGE}"))?;
+                    options.think = Duration::from_millis(ms);
+                }
+                "--help" | "-h" => {
+                    let net_usage = NetConfig::from_args([flag]).err().unwrap_or_default();
+                    return Err(format!("{USAGE}\n{net_usage}"));
+                }
+                _ => rest.push(flag),
+            }
+        }
+        Ok((options, rest))
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (options, config) = BotOptions::from_args(args)
+        .and_then(|(options, rest)| Ok((options, NetConfig::from_args(rest)?)))
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            std::process::exit(2);
+        });
+    let mut bots: Vec<App> = (0..options.count)
+        .map(|index| {
+            let mut config = config.clone();
+            config.player_name = format!("{}{}", config.player_name, index);
+            bot_app(config, &options)
+        })
+        .collect();
+    println!("running {} {:?} bots", bots.len(), options.behavior);
+    loop {
+        for bot in bots.iter_mut() {
+            bot.update();
+        }
+        std::thread::sleep(FRAME);
+    }
+}
+
+fn bot_app(config: NetConfig, options: &BotOptions) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugin(ClientNetPlugin);
+    load_data(&mut app).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+    let client = new_renet_client(&config).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+    app.insert_resource(client);
+    app.insert_resource(config);
+    // spread the bots' decisions out instead of all clicking at once
+    let mut timer = Timer::new(options.think, TimerMode::Repeating);
+    timer.set_elapsed(options.think.mul_f32(rand::thread_rng().gen()));
+    app.insert_resource(Bot {
+        behavior: options.behavior,
+        think: timer,
+    });
+    app.add_system(think);
+    app
+}
+
+/// Decides what the bot does next, through the same events the mouse and
+/// keyboard produce on the real client.
+#[allow(clippy::too_many_arguments)]
+fn think(
+    time: Res<Time>,
+    mut bot: ResMut<Bot>,
+    player: Query<&Tile, With<ControlledEntity>>,
+    entities: Query<(Entity, &Tile, &EntityType)>,
+    grid: Res<NavGrid>,
+    archetypes: Res<Archetypes>,
+    mut click_event: EventWriter<ClickEvent>,
+    mut player_command: EventWriter<PlayerCommand>,
+) {
+    if !bot.think.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Ok(origin) = player.get_single() else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    match bot.behavior.pick(&mut rng) {
+        Behavior::Wander => {
+            let destination = entities
+                .iter()
+                .filter(|(_, tile, entity_type)| {
+                    **entity_type == EntityType::Tile && grid.is_walkable(tile)
+                })
+                .choose(&mut rng);
+            if let Some((entity, tile, _)) = destination {
+                if grid.find_path(*origin, *tile, false).is_some() {
+                    click_event.send(ClickEvent::new(entity, LeftClick::Walk, *tile));
+                }
+            }
+        }
+        Behavior::Hunt => {
+            let mob = entities
+                .iter()
+                .filter(|(_, _, entity_type)| {
+                    archetypes
+                        .of(entity_type)
+                        .is_some_and(|archetype| archetype.ai.is_some())
+                })
+                .min_by_key(|(_, tile, _)| distance(origin, tile));
+            if let Some((entity, tile, _)) = mob {
+                click_event.send(ClickEvent::new(entity, LeftClick::Attack(entity), *tile));
+                player_command.send(PlayerCommand::UseAbility(
+                    AbilityId::AUTO_ATTACK,
+                    Some(entity),
+                ));
+            }
+        }
+        Behavior::Spam | Behavior::Mixed => {
+            player_command.send(PlayerCommand::UseAbility(AbilityId::AUTO_ATTACK, None))
+        }
+    }
+}
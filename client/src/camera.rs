@@ -1,11 +1,10 @@
 use bevy_mod_picking::prelude::*;
-use std::f32::consts::PI;
 
 use bevy::prelude::*;
 use lib::components::ControlledEntity;
 use smooth_bevy_cameras::{
     controllers::orbit::{OrbitCameraBundle, OrbitCameraController},
-    LookTransform, LookTransformBundle, Smoother,
+    LookTransform,
 };
 pub fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera3dBundle::default()).insert((
@@ -29,10 +28,8 @@ pub fn camera_follow(
 ) {
     let mut cam_transform = camera_query.single_mut();
     if let Ok(player_transform) = player_query.get_single() {
-        cam_transform.eye.x =
-            cam_transform.eye.x + (player_transform.translation.x - cam_transform.target.x);
-        cam_transform.eye.z =
-            cam_transform.eye.z + (player_transform.translation.z - cam_transform.target.z);
+        cam_transform.eye.x += player_transform.translation.x - cam_transform.target.x;
+        cam_transform.eye.z += player_transform.translation.z - cam_transform.target.z;
         cam_transform.target = player_transform.translation;
     }
 }
@@ -3,8 +3,14 @@ use bevy_renet::renet::RenetConnectionConfig;
 use bevy_renet::renet::{ClientAuthentication, RenetClient};
 use lib::channels::{ClientChannel, ServerChannel};
 use lib::components::ServerMessages;
-use lib::PROTOCOL_ID;
-use std::{net::UdpSocket, time::SystemTime};
+use lib::{
+    auth::{request_token, UserData},
+    config::NetConfig,
+};
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::SystemTime,
+};
 
 use crate::resources::{ClientInfo, ClientLobby};
 
@@ -15,22 +21,40 @@ pub fn client_connection_config() -> RenetConnectionConfig {
         ..Default::default()
     }
 }
-pub fn new_renet_client() -> RenetClient {
-    let server_addr = "127.0.0.1:5000".parse().unwrap();
-    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+pub fn new_renet_client(config: &NetConfig) -> Result<RenetClient, String> {
+    let client_id = rand::random::<u64>();
+    println!("client_id: {:?}", client_id);
+    renet_client(config, client_id)
+}
+
+/// A client connecting as `client_id`, which only counts in unsecure
+/// mode, the auth service picks the id otherwise. Fails when there is no
+/// token or socket.
+pub fn renet_client(config: &NetConfig, client_id: u64) -> Result<RenetClient, String> {
+    let server_addr = config.public_socket_addr();
+    let bind_addr = SocketAddr::new(config.bind_addr, 0);
+    let socket = UdpSocket::bind(bind_addr).map_err(|err| format!("binding {bind_addr}: {err}"))?;
     let connection_config = client_connection_config();
     let current_time = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
-    let client_id = current_time.as_millis() as u64;
-    println!("client_id: {:?}", client_id);
-    let authentication = ClientAuthentication::Unsecure {
-        client_id,
-        protocol_id: PROTOCOL_ID,
-        server_addr,
-        user_data: None,
+    let authentication = if config.secure {
+        let connect_token = request_token(config)?;
+        ClientAuthentication::Secure { connect_token }
+    } else {
+        let user_data = UserData {
+            account_id: client_id,
+            name: config.player_name.clone(),
+        };
+        ClientAuthentication::Unsecure {
+            client_id,
+            protocol_id: config.protocol_id,
+            server_addr,
+            user_data: Some(user_data.to_bytes()),
+        }
     };
-    RenetClient::new(current_time, socket, connection_config, authentication).unwrap()
+    RenetClient::new(current_time, socket, connection_config, authentication)
+        .map_err(|err| format!("starting the client: {err}"))
 }
 
 pub fn server_messages(mut client: ResMut<RenetClient>, mut lobby: ResMut<ClientLobby>) {
@@ -38,9 +62,15 @@ pub fn server_messages(mut client: ResMut<RenetClient>, mut lobby: ResMut<Client
         let server_message: ServerMessages = bincode::deserialize(&message).unwrap();
 
         match server_message {
-            ServerMessages::PlayerConnected { id } => {
-                println!("Player {} connected", id);
-                lobby.clients.insert(id, ClientInfo::default());
+            ServerMessages::PlayerConnected { id, name } => {
+                println!("Player {} ({}) connected", id, name);
+                lobby.clients.insert(
+                    id,
+                    ClientInfo {
+                        name,
+                        ..Default::default()
+                    },
+                );
             }
 
             ServerMessages::PlayerDisconnected { id } => {
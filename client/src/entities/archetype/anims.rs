@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+
+/// The animation an archetype's scene loops.
+#[derive(Component)]
+pub struct IdleAnimation(pub Handle<AnimationClip>);
+
+/// Starts the idle animation of the archetype an AnimationPlayer belongs
+/// to, the player sits somewhere below it in the scene.
+pub fn idle_animations(
+    mut animation_players: Query<(Entity, &mut AnimationPlayer), Added<AnimationPlayer>>,
+    parents: Query<&Parent>,
+    idle: Query<&IdleAnimation>,
+) {
+    for (entity, mut player) in animation_players.iter_mut() {
+        let animation = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| idle.get(ancestor).ok());
+        if let Some(animation) = animation {
+            player.play(animation.0.clone_weak()).repeat();
+        }
+    }
+}
@@ -1,3 +1,2 @@
-pub mod spawn;
-pub mod extra;
 pub mod anims;
+pub mod spawn;
@@ -0,0 +1,73 @@
+use bevy::{gltf::Gltf, prelude::*};
+use lib::{
+    archetypes::{Archetypes, Model},
+    components::{EntityType, Tile},
+};
+
+use super::anims::IdleAnimation;
+
+/// An archetype whose model isn't attached yet, GLTF scenes wait here
+/// until their file finished loading.
+#[derive(Component)]
+pub struct PendingModel;
+
+/// Attaches the model the archetype asks for.
+pub fn spawn_archetype_models(
+    mut commands: Commands,
+    pending: Query<(Entity, &EntityType, &Tile), With<PendingModel>>,
+    archetypes: Res<Archetypes>,
+    asset_server: Res<AssetServer>,
+    assets: Res<Assets<Gltf>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (entity, entity_type, tile) in pending.iter() {
+        let transform = tile.to_transform();
+        match archetypes
+            .of(entity_type)
+            .and_then(|archetype| archetype.model.as_ref())
+        {
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(SpatialBundle::from_transform(transform));
+            }
+            Some(Model::Capsule((r, g, b))) => {
+                commands.entity(entity).insert(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Capsule::default())),
+                    material: materials.add(Color::rgb(*r, *g, *b).into()),
+                    transform,
+                    ..Default::default()
+                });
+            }
+            Some(Model::Cube(size, (r, g, b))) => {
+                commands.entity(entity).insert(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Cube { size: *size })),
+                    material: materials.add(Color::rgb(*r, *g, *b).into()),
+                    transform,
+                    ..Default::default()
+                });
+            }
+            Some(Model::Scene { gltf, scene, idle }) => {
+                // the asset server hands out the same handle while loading
+                let Some(gltf) = assets.get(&asset_server.load(gltf.as_str())) else {
+                    continue;
+                };
+                let Some(scene) = gltf.named_scenes.get(scene.as_str()) else {
+                    warn!("{:?} has no scene {:?}", entity_type, scene);
+                    commands.entity(entity).remove::<PendingModel>();
+                    continue;
+                };
+                commands.entity(entity).insert(SceneBundle {
+                    scene: scene.clone(),
+                    transform,
+                    ..Default::default()
+                });
+                if let Some(clip) = idle.and_then(|idle| gltf.animations.get(idle)) {
+                    commands.entity(entity).insert(IdleAnimation(clip.clone()));
+                }
+            }
+        }
+        commands.entity(entity).remove::<PendingModel>();
+    }
+}
@@ -1,5 +1,4 @@
+pub mod archetype;
+pub mod door;
 pub mod player;
-pub mod slime;
 pub mod wall;
-pub mod door;
-pub mod extra;
@@ -1,20 +1,8 @@
 use bevy::prelude::*;
 use core::any::type_name;
 use leafwing_input_manager::prelude::*;
-use lib::components::{Action, Idle, Player, PlayerCommand, Running, Tile};
+use lib::components::{Idle, Player, Running, Tile};
 use seldom_state::prelude::*;
-pub fn auto_attack(
-    //query: Query<&ActionState<Action>, (With<Player>, With<Target>)>,
-    query: Query<&ActionState<Action>>,
-    mut player_command: EventWriter<PlayerCommand>,
-) {
-    if let Ok(action_state) = query.get_single() {
-        if action_state.just_pressed(Action::AutoAttack) {
-            println!("auto attack!");
-            player_command.send(PlayerCommand::AutoAttack);
-        }
-    }
-}
 
 #[derive(Default, Clone, Copy, Component, Reflect)]
 #[reflect(Component)]
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use lib::{
+    equipment::{EquipSlot, Equipment},
+    items::{ItemId, Items},
+};
+
+/// The sword baked into the character model, hidden for the equipped one.
+const BAKED_WEAPON: &str = "great_sword";
+
+const HAND: &str = "hand_hold.R";
+
+/// The bone an equipment slot's model hangs off, armor has no model yet.
+fn bone(slot: EquipSlot) -> Option<&'static str> {
+    match slot {
+        EquipSlot::MainHand => Some(HAND),
+        EquipSlot::OffHand => Some("hand_hold.L"),
+        EquipSlot::Armor => None,
+    }
+}
+
+/// The models attached to a player by slot, `ready` once the skeleton
+/// was found.
+#[derive(Component, Default)]
+pub struct AttachedModels {
+    models: Vec<(EquipSlot, ItemId, Entity)>,
+    ready: bool,
+}
+
+/// Attaches the model of every equipped item to its bone and removes the
+/// ones no longer equipped. The character scene spawns its bones a few
+/// frames after the player, until then this keeps retrying.
+pub fn attach_equipment(
+    mut commands: Commands,
+    mut players: Query<(Entity, &Equipment, Option<&mut AttachedModels>)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    items: Res<Items>,
+    asset_server: Res<AssetServer>,
+) {
+    for (player, equipment, attached) in players.iter_mut() {
+        let mut attached = match attached {
+            Some(attached) => attached,
+            None => {
+                commands.entity(player).insert(AttachedModels::default());
+                continue;
+            }
+        };
+        // only what has a bone to hang off, or it never counts as attached
+        let wanted = |slot: EquipSlot| {
+            bone(slot)?;
+            let item = equipment.get(slot)?;
+            items.get(item)?.model.as_ref()?;
+            Some(item)
+        };
+        let up_to_date = EquipSlot::ALL.iter().all(|slot| {
+            let current = attached
+                .models
+                .iter()
+                .find(|(attached_slot, ..)| attached_slot == slot)
+                .map(|(_, item, _)| *item);
+            current == wanted(*slot)
+        });
+        if attached.ready && up_to_date {
+            continue;
+        }
+        // the scene hasn't spawned the skeleton yet
+        if find_named(player, HAND, &children, &names).is_none() {
+            continue;
+        }
+        if let Some(baked) = find_named(player, BAKED_WEAPON, &children, &names) {
+            commands.entity(baked).insert(Visibility::Hidden);
+        }
+        for (_, _, model) in attached.models.drain(..) {
+            commands.entity(model).despawn_recursive();
+        }
+        for slot in EquipSlot::ALL {
+            let (Some(item), Some(bone)) = (wanted(slot), bone(slot)) else {
+                continue;
+            };
+            let Some(bone) = find_named(player, bone, &children, &names) else {
+                continue;
+            };
+            let Some(path) = items.get(item).and_then(|item| item.model.clone()) else {
+                continue;
+            };
+            let model = commands
+                .spawn(SceneBundle {
+                    scene: asset_server.load(path),
+                    ..Default::default()
+                })
+                .id();
+            commands.entity(bone).add_child(model);
+            attached.models.push((slot, item, model));
+        }
+        attached.ready = true;
+    }
+}
+
+/// The descendant of `entity` called `name`.
+fn find_named(
+    entity: Entity,
+    name: &str,
+    children: &Query<&Children>,
+    names: &Query<&Name>,
+) -> Option<Entity> {
+    if names.get(entity).is_ok_and(|found| found.as_str() == name) {
+        return Some(entity);
+    }
+    children
+        .get(entity)
+        .ok()?
+        .iter()
+        .find_map(|child| find_named(*child, name, children, names))
+}
@@ -1,4 +1,5 @@
 pub mod anims;
-pub mod pathing;
-pub mod healthbar;
 pub mod control;
+pub mod equipment;
+pub mod healthbar;
+pub mod pathing;
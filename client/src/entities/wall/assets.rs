@@ -1,4 +1,4 @@
-use bevy::{prelude::*, gltf::Gltf};
+use bevy::{gltf::Gltf, prelude::*};
 
 #[derive(Resource)]
 pub struct WallAssetPack(pub Handle<Gltf>);
@@ -1,4 +1,4 @@
-use lib::components::{Wall, Tile};
+use lib::components::{Tile, Wall};
 
 pub struct SpawnWallEvent {
     pub wall: Wall,
@@ -1,3 +1,3 @@
-pub mod spawn;
-pub mod extra;
 pub mod assets;
+pub mod extra;
+pub mod spawn;
@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+use entities::wall::extra::SpawnWallEvent;
+use leafwing_input_manager::prelude::*;
+
+pub mod action_bar;
+pub mod assets;
+pub mod camera;
+pub mod components;
+pub mod connection;
+pub mod entities;
+pub mod input;
+pub mod movement;
+pub mod plugins;
+pub mod receive;
+pub mod render;
+pub mod resources;
+pub mod run_conditions;
+pub mod status;
+pub mod sync;
+
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum Move {
+    North,
+    South,
+    West,
+    East,
+}
+
+#[derive(Resource, Default)]
+pub struct Animations(pub Vec<Handle<AnimationClip>>);
@@ -1,24 +1,41 @@
 use bevy::prelude::*;
 use bevy_renet::renet::RenetClient;
 use lib::{
+    abilities::{Abilities, AbilityTarget},
     channels::ClientChannel,
-    components::{ControlledEntity, Direction, LeftClick, Path, PlayerCommand, Tile},
+    combat::{AttackRange, DeathEvent},
+    components::{
+        ControlledEntity, InputAck, LeftClick, Path, PlayerCommand, PlayerInput, Target, Tile,
+    },
+    net_id::{NetIds, NetMapped},
     resources::Tick,
     ClickEvent,
 };
 
-use crate::resources::NetworkMapping;
+/// Sends the click to the server, which plans and walks the path, and plans
+/// the same path locally to predict it.
 pub fn get_path(
     mut commands: Commands,
     mut walk_event: EventReader<ClickEvent>,
-    query: Query<(Entity, &Tile), With<ControlledEntity>>,
+    mut query: Query<(Entity, &Tile, &mut Prediction), With<ControlledEntity>>,
+    mut player_commands: EventWriter<PlayerCommand>,
 ) {
     for event in walk_event.iter() {
-        if let Ok((entity, origin)) = query.get_single() {
+        if let Ok((entity, origin, mut prediction)) = query.get_single_mut() {
+            player_commands.send(PlayerCommand::LeftClick(
+                event.left_click,
+                event.destination,
+            ));
+
+            prediction.offset = 0;
+            prediction.destination = event.destination;
+            prediction.left_click = event.left_click;
+            prediction.range = AttackRange::Melee;
             let path = Path {
                 destination: event.destination,
                 origin: *origin,
                 left_click: event.left_click,
+                range: AttackRange::Melee,
             };
             commands.entity(entity).insert(path);
         }
@@ -29,57 +46,52 @@ pub fn get_path(
 pub struct PathMap {
     pub steps: Vec<(Tick, LeftClick, Tile)>,
 }
+/// The path the client predicts the server walks for input `sequence`.
+/// `steps` starts after `origin`, which is where the server is after
+/// `offset` steps.
+#[derive(Clone, Debug, Component, Default)]
+pub struct Prediction {
+    pub sequence: u64,
+    pub offset: u32,
+    pub origin: Tile,
+    pub steps: Vec<Tile>,
+    pub destination: Tile,
+    pub left_click: LeftClick,
+    pub range: AttackRange,
+}
+
+impl Prediction {
+    /// Where the player should be after the server walked `step` steps,
+    /// `None` for steps from before the path was planned again.
+    fn tile_at(&self, step: u32) -> Option<Tile> {
+        if step < self.offset {
+            return None;
+        }
+        let walked = (step - self.offset) as usize;
+        if walked == 0 {
+            return Some(self.origin);
+        }
+        // past the end of the prediction the player should have stopped
+        let predicted = self.steps.get(walked - 1).or(self.steps.last());
+        Some(*predicted.unwrap_or(&self.origin))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct InputSequence(pub u64);
+
 pub fn scheduled_movement(
-    mut query: Query<&mut PathMap>,
+    mut query: Query<(&mut PathMap, &mut Tile), With<ControlledEntity>>,
     game_tick: ResMut<Tick>,
-    mut player_commands: EventWriter<PlayerCommand>,
-    mut commands: Commands,
-    mut network_mapping: ResMut<NetworkMapping>,
 ) {
-    if let Ok(mut path_map) = query.get_single_mut() {
+    if let Ok((mut path_map, mut predicted_tile)) = query.get_single_mut() {
         path_map.steps.retain(|(scheduled_tick, left_click, tile)| {
             if scheduled_tick.tick <= game_tick.tick {
                 //player_commands.send(PlayerCommand::LeftClick(*left_click, *tile));
-                match left_click {
-                    LeftClick::Pickup(Some(e)) => {
-                        //println!("some e: {:?}", e);
-                        if let Some(server_entity) = network_mapping.client.remove(e) {
-                            player_commands.send(PlayerCommand::LeftClick(
-                                LeftClick::Pickup(Some(server_entity)),
-                                *tile,
-                            ));
-                            //println!("command send pickup {:?}", e);
-                        }
-                        //delete_writer.send(DeleteMe(*e));
-                        commands.entity(*e).despawn_recursive();
-                    }
-                    LeftClick::Attack(e) =>{
-                        if let Some(server_entity) = network_mapping.client.get(e){
-                            println!("attack");
-                            player_commands.send(PlayerCommand::LeftClick(LeftClick::Attack(*server_entity), *tile));
-                        }
-                    }
-
-                    LeftClick::Open(e) =>{
-                        if let Some(server_entity) = network_mapping.client.get(e){
-                            println!("open");
-                            player_commands.send(PlayerCommand::LeftClick(LeftClick::Open(*server_entity), *tile));
-                        }
-                    }
-                    _=> {
-                        //println!("walk");
-                        player_commands.send(PlayerCommand::LeftClick(*left_click, *tile));
-                    }
-                    //LeftClick::Pull => {
-                        //player_commands.send(PlayerCommand::LeftClick(*left_click, *tile));
-                        //println!("PULL");
-                    //}
-
-                    //LeftClick::Attack => {
-                        //player_commands.send(PlayerCommand::LeftClick(*left_click, *tile));
-                        //println!("PULL");
-                    //}
-                    //_ => (),
+                if *left_click == LeftClick::Walk {
+                    // predict the step, reconcile() corrects it if the
+                    // server disagrees
+                    *predicted_tile = *tile;
                 }
                 false // Remove the current element from the vector
             } else {
@@ -90,14 +102,140 @@ pub fn scheduled_movement(
     //}
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn client_send_player_commands(
+    mut commands: Commands,
     mut player_commands: EventReader<PlayerCommand>,
     mut client: ResMut<RenetClient>,
+    mut sequence: ResMut<InputSequence>,
+    mut prediction: Query<
+        (Entity, &Tile, Option<&Target>, &mut Prediction),
+        With<ControlledEntity>,
+    >,
+    tiles: Query<&Tile>,
+    abilities: Res<Abilities>,
+    ids: Res<NetIds>,
 ) {
-    for command in player_commands.iter() {
-        let command_message = bincode::serialize(command).unwrap();
+    for player_command in player_commands.iter() {
+        let Some(command) = player_command.to_net(&ids) else {
+            continue;
+        };
+        sequence.0 += 1;
+        if let Ok((entity, origin, target, mut prediction)) = prediction.get_single_mut() {
+            match player_command {
+                PlayerCommand::LeftClick(..) => prediction.sequence = sequence.0,
+                // the server walks into range of the target first, predict
+                // that like a click on it
+                PlayerCommand::UseAbility(ability, ability_target) => {
+                    let Some(ability) = abilities.get(*ability) else {
+                        continue;
+                    };
+                    let target = match ability.target {
+                        AbilityTarget::Caster => Some(entity),
+                        AbilityTarget::Enemy => {
+                            (*ability_target).or(target.and_then(|target| target.0))
+                        }
+                    };
+                    if let Some((target, destination)) =
+                        target.and_then(|target| Some((target, tiles.get(target).ok()?)))
+                    {
+                        let left_click = if target == entity {
+                            LeftClick::Walk
+                        } else {
+                            LeftClick::Attack(target)
+                        };
+                        prediction.sequence = sequence.0;
+                        prediction.offset = 0;
+                        prediction.destination = *destination;
+                        prediction.left_click = left_click;
+                        prediction.range = ability.range;
+                        commands.entity(entity).insert(Path {
+                            destination: *destination,
+                            origin: *origin,
+                            left_click,
+                            range: ability.range,
+                        });
+                    }
+                }
+                // the inventory only changes once the server says so
+                PlayerCommand::DropItem(_)
+                | PlayerCommand::MoveItem(..)
+                | PlayerCommand::SplitStack(..)
+                | PlayerCommand::Equip(_)
+                | PlayerCommand::Unequip(_) => (),
+            }
+        }
+        let input = PlayerInput {
+            sequence: sequence.0,
+            command,
+        };
+        let command_message = bincode::serialize(&input).unwrap();
         client.send_message(ClientChannel::Command, command_message);
 
         //println!("send");
     }
 }
+
+/// Compares the tile the server reports with where the prediction had the
+/// player after the same number of steps. On a mismatch the player is put
+/// back on the authoritative tile and the rest of the path is planned
+/// again from there.
+pub fn reconcile(
+    mut acks: EventReader<InputAck>,
+    mut query: Query<(Entity, &mut Tile, &mut Prediction), With<ControlledEntity>>,
+    mut commands: Commands,
+) {
+    let Some(ack) = acks.iter().max_by_key(|ack| (ack.sequence, ack.step)) else {
+        return;
+    };
+    let Ok((entity, mut tile, mut prediction)) = query.get_single_mut() else {
+        return;
+    };
+    // the server hasn't seen the latest click yet
+    if ack.sequence != prediction.sequence {
+        return;
+    }
+    if prediction.sequence == 0 {
+        // nothing predicted, follow the server
+        if *tile != ack.tile {
+            *tile = ack.tile;
+        }
+        return;
+    }
+    let Some(predicted) = prediction.tile_at(ack.step) else {
+        return;
+    };
+    if predicted == ack.tile {
+        return;
+    }
+    //println!("mispredicted {:?}, server has {:?}", predicted, ack.tile);
+    *tile = ack.tile;
+    prediction.offset = ack.step;
+    commands.entity(entity).insert((
+        PathMap::default(),
+        Path {
+            destination: prediction.destination,
+            origin: ack.tile,
+            left_click: prediction.left_click,
+            range: prediction.range,
+        },
+    ));
+}
+
+/// The server respawned the player and dropped its path, so stop
+/// predicting and follow the InputAcks until the next click.
+pub fn reset_prediction(
+    mut death_event: EventReader<DeathEvent>,
+    mut query: Query<(Entity, &mut Prediction), With<ControlledEntity>>,
+    mut commands: Commands,
+) {
+    let Ok((entity, mut prediction)) = query.get_single_mut() else {
+        return;
+    };
+    for event in death_event.iter() {
+        if event.entity == entity {
+            *prediction = Prediction::default();
+            commands.entity(entity).remove::<(PathMap, Path)>();
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use bevy_easings::*;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_mod_picking::prelude::*;
+use bevy_renet::RenetClientPlugin;
+use leafwing_input_manager::prelude::*;
+use lib::{
+    abilities::Abilities,
+    archetypes::{mark_blockers, Archetypes},
+    combat::{DamageEvent, DeathEvent},
+    components::{
+        Action, DespawnEvent, Health, InputAck, PlayerCommand, SpawnEvent, TickEvent, Tile,
+    },
+    items::Items,
+    nav::{update_nav_grid, NavGrid},
+    replication::{ReplicationPlugin, Snapshot},
+    resources::Tick,
+    ClickEvent, OpenEvent,
+};
+use seldom_state::prelude::*;
+use smooth_bevy_cameras::{controllers::orbit::OrbitCameraPlugin, LookTransformPlugin};
+
+use crate::{
+    action_bar::{spawn_action_bar, update_action_bar, use_action_bar},
+    assets::{load_anims, should_load_anims, ManAssetPack, ShouldLoadAnims},
+    camera::{camera_follow, setup_camera},
+    connection::server_messages,
+    entities::{
+        archetype::{anims::idle_animations, spawn::spawn_archetype_models},
+        door::control::open_door,
+        player::{
+            anims::setup_anims, control::Moving, equipment::attach_equipment,
+            healthbar::update_health_bar, pathing::find_path,
+        },
+        wall::{assets::WallAssetPack, extra::SpawnWallEvent, spawn::dg_wall},
+    },
+    input::{make_pickable, mouse_input, PickingEvent},
+    movement::{
+        client_send_player_commands, get_path, reconcile, reset_prediction, scheduled_movement,
+        InputSequence,
+    },
+    receive::{ack_message, lifecycle_message, server_events, snapshot_message, tick},
+    render::{float_damage_numbers, move_to_tile, spawn, spawn_damage_numbers, swing_door},
+    resources::{ClientLobby, NetworkMapping, SnapshotBuffer},
+    status::{follow_status_icons, tint_status, update_status_icons},
+    sync::update,
+    Animations,
+};
+
+/// Keeps the replicated world in sync with the server and sends the
+/// player's commands, predicting their movement. Needs no window or
+/// assets, only a `RenetClient` resource, so bots and tests run it too.
+pub struct ClientNetPlugin;
+
+/// The data files client and server have to agree on, from the working
+/// directory when they're there, instead of the built in copies
+/// ClientNetPlugin starts with.
+pub fn load_data(app: &mut App) -> Result<(), String> {
+    app.insert_resource(Abilities::load()?);
+    app.insert_resource(Items::load()?);
+    app.insert_resource(Archetypes::load()?);
+    Ok(())
+}
+
+impl Plugin for ClientNetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RenetClientPlugin {
+            clear_events: false,
+        });
+        app.add_plugin(ReplicationPlugin::client());
+        app.insert_resource(Tick::default());
+        app.insert_resource(NetworkMapping::default());
+        app.insert_resource(SnapshotBuffer::default());
+        app.insert_resource(ClientLobby::default());
+        app.insert_resource(NavGrid::default());
+        app.insert_resource(InputSequence::default());
+        app.init_resource::<Abilities>();
+        app.init_resource::<Items>();
+        app.init_resource::<Archetypes>();
+        app.add_system(tick);
+        app.add_system(server_messages);
+        app.add_system(lifecycle_message);
+        app.add_system(snapshot_message);
+        app.add_system(ack_message);
+        app.add_system(update.after(snapshot_message).after(lifecycle_message));
+        app.add_system(server_events);
+        app.add_system(reconcile.after(ack_message));
+        app.add_system(reset_prediction.after(server_events).before(reconcile));
+        app.add_system(get_path);
+        // its inserts have to land before a despawn in the same frame
+        app.add_system(mark_blockers.before(lifecycle_message));
+        app.add_system(update_nav_grid.before(find_path));
+        app.add_system(find_path);
+        app.add_system(scheduled_movement);
+        app.add_system(client_send_player_commands);
+        app.add_event::<ClickEvent>();
+        app.add_event::<PlayerCommand>();
+        app.add_event::<SpawnEvent>();
+        app.add_event::<DespawnEvent>();
+        app.add_event::<Snapshot>();
+        app.add_event::<InputAck>();
+        app.add_event::<TickEvent>();
+        app.add_event::<OpenEvent>();
+        app.add_event::<DamageEvent>();
+        app.add_event::<DeathEvent>();
+    }
+}
+
+/// Models, animations, input and the camera for what ClientNetPlugin
+/// replicates, visuals are attached when an `EntityType` is added.
+pub struct ClientRenderPlugin;
+
+impl Plugin for ClientRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(InputManagerPlugin::<Action>::default());
+        app.add_plugin(EasingsPlugin);
+        app.add_plugin(OrbitCameraPlugin::default());
+        app.add_plugins(
+            DefaultPickingPlugins
+                .build()
+                .disable::<DebugPickingPlugin>(),
+        );
+        app.add_plugin(StateMachinePlugin);
+        app.add_plugin(TriggerPlugin::<Moving>::default());
+        app.add_plugin(WorldInspectorPlugin::default());
+        app.add_plugin(LookTransformPlugin);
+
+        app.add_startup_system(setup_camera);
+        app.add_system(dg_wall);
+        app.add_system(camera_follow);
+        app.insert_resource(Animations::default());
+        app.insert_resource(ShouldLoadAnims(true));
+        app.init_resource::<ManAssetPack>();
+        app.init_resource::<WallAssetPack>();
+        app.add_system(make_pickable);
+        app.add_system(mouse_input);
+        app.add_system(spawn);
+        app.add_system(move_to_tile.after(update));
+        app.add_system(swing_door.after(update));
+        app.add_system(setup_anims);
+        app.add_system(open_door);
+        app.add_system(use_action_bar);
+        app.add_system(spawn_action_bar);
+        app.add_system(update_action_bar);
+        app.add_system(update_health_bar);
+        app.add_system(spawn_archetype_models);
+        app.add_system(load_anims.run_if(should_load_anims));
+        app.add_system(idle_animations);
+        app.add_system(spawn_damage_numbers);
+        app.add_system(float_damage_numbers);
+        app.add_system(update_status_icons);
+        app.add_system(follow_status_icons);
+        app.add_system(tint_status);
+        app.add_system(attach_equipment);
+        app.add_event::<PickingEvent>();
+        app.add_event::<SpawnWallEvent>();
+        app.register_type::<Tile>();
+        app.register_type::<Health>();
+    }
+}
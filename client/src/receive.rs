@@ -1,84 +1,140 @@
-use bevy::prelude::{Commands, DespawnRecursiveExt, Entity, EventWriter, Res, ResMut};
+use bevy::prelude::{
+    Commands, DespawnRecursiveExt, Entity, EventWriter, Local, Query, Res, ResMut, With,
+};
 use bevy_renet::renet::RenetClient;
 use lib::{
     channels::ServerChannel,
-    components::{EntityType, SpawnEvent, Tile, UpdateEvent},
+    combat::{DamageEvent, DeathEvent},
+    components::{
+        ControlledEntity, EntityType, InputAck, LifecycleEvent, LifecycleMessage, SpawnEvent,
+    },
+    items::Inventory,
+    net_id::{NetIds, NetMapped},
+    replication::Snapshot,
     resources::Tick,
+    OpenEvent, ServerEvents,
 };
 
-use crate::resources::NetworkMapping;
+use crate::{movement::Prediction, resources::NetworkMapping};
 
-pub fn load_message(
+/// Spawns and despawns entities in the order the server sent them, all
+/// messages that arrived this frame are handled. Spawned entities get
+/// their EntityType and Tile, visuals are up to whoever renders them.
+pub fn lifecycle_message(
     mut client: ResMut<RenetClient>,
     mut spawn_event: EventWriter<SpawnEvent>,
     mut network_mapping: ResMut<NetworkMapping>,
+    mut ids: ResMut<NetIds>,
     mut commands: Commands,
 ) {
-    if let Some(message) = client.receive_message(ServerChannel::Load) {
-        //println!("received load message");
-        let load_message: Vec<(Entity, EntityType, Tile)> = bincode::deserialize(&message).unwrap();
-        for (server_entity, entity_type, tile) in load_message {
-            if network_mapping.server.get(&server_entity).is_none() {
-                let entity = commands.spawn_empty().id();
-                network_mapping.add(&entity, &server_entity);
-                spawn_event.send(SpawnEvent {
-                    entity,
-                    entity_type,
-                    tile,
-                });
+    while let Some(message) = client.receive_message(ServerChannel::Lifecycle) {
+        let message: LifecycleMessage = bincode::deserialize(&message).unwrap();
+        network_mapping.tick = message.tick;
+        for event in message.events {
+            match event {
+                LifecycleEvent::Spawn(id, entity_type, tile) => {
+                    if ids.entity(id).is_some() {
+                        continue;
+                    }
+                    let entity = commands.spawn((entity_type, tile)).id();
+                    if let EntityType::Player(player) = entity_type {
+                        if player.id == client.client_id() {
+                            commands
+                                .entity(entity)
+                                .insert((ControlledEntity, Prediction::default()));
+                        }
+                    }
+                    ids.insert(id, entity);
+                    spawn_event.send(SpawnEvent {
+                        entity,
+                        entity_type,
+                        tile,
+                    });
+                }
+                LifecycleEvent::Despawn(id) => {
+                    network_mapping.pending.remove(&id);
+                    if let Some(entity) = ids.remove(id) {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
             }
         }
     }
 }
 
-pub fn spawn_message(
-    mut client: ResMut<RenetClient>,
-    mut spawn_event: EventWriter<SpawnEvent>,
-    mut network_mapping: ResMut<NetworkMapping>,
-    mut commands: Commands,
-) {
-    if let Some(message) = client.receive_message(ServerChannel::Spawn) {
-        let spawn_message: SpawnEvent = bincode::deserialize(&message).unwrap();
-        if network_mapping.server.get(&spawn_message.entity).is_none() {
-            let entity = commands.spawn_empty().id();
-            network_mapping.add(&entity, &spawn_message.entity);
-            spawn_event.send(SpawnEvent {
-                entity,
-                entity_type: spawn_message.entity_type,
-                tile: spawn_message.tile,
-            });
+pub fn snapshot_message(mut client: ResMut<RenetClient>, mut snapshots: EventWriter<Snapshot>) {
+    while let Some(message) = client.receive_message(ServerChannel::Update) {
+        if let Ok(snapshot) = bincode::deserialize::<Snapshot>(&message) {
+            snapshots.send(snapshot);
         }
     }
 }
-pub fn update_message(
-    mut client: ResMut<RenetClient>,
-    mut update_event: EventWriter<UpdateEvent>,
-    network_mapping: Res<NetworkMapping>,
-) {
-    if let Some(message) = client.receive_message(ServerChannel::Update) {
-        let mut update_message: UpdateEvent = bincode::deserialize(&message).unwrap();
-        if let Some(entity) = network_mapping.server.get(&update_message.entity) {
-            update_message.entity = *entity;
-            update_event.send(update_message);
-        }
+
+pub fn tick(mut client: ResMut<RenetClient>, mut tick: ResMut<Tick>) {
+    if let Some(message) = client.receive_message(ServerChannel::Tick) {
+        let new_tick: Tick = bincode::deserialize(&message).unwrap();
+        tick.tick = new_tick.tick;
     }
 }
-pub fn despawn_message(
+
+pub fn ack_message(mut client: ResMut<RenetClient>, mut ack_event: EventWriter<InputAck>) {
+    while let Some(message) = client.receive_message(ServerChannel::InputAck) {
+        let ack: InputAck = bincode::deserialize(&message).unwrap();
+        ack_event.send(ack);
+    }
+}
+
+/// Turns what arrives on ServerChannel::ServerEvents back into local
+/// events, dropping those about entities this client doesn't have.
+#[allow(clippy::too_many_arguments)]
+pub fn server_events(
     mut client: ResMut<RenetClient>,
-    mut network_mapping: ResMut<NetworkMapping>,
+    ids: Res<NetIds>,
+    mut open_event: EventWriter<OpenEvent>,
+    mut damage_event: EventWriter<DamageEvent>,
+    mut death_event: EventWriter<DeathEvent>,
     mut commands: Commands,
+    controlled: Query<Entity, With<ControlledEntity>>,
+    mut inventory_tick: Local<u64>,
+    mut pending_inventory: Local<Option<Inventory>>,
 ) {
-    if let Some(message) = client.receive_message(ServerChannel::Despawn) {
-        let despawn_entity: Entity = bincode::deserialize(&message).unwrap();
-        if let Some(entity) = network_mapping.server.remove(&despawn_entity) {
-            commands.entity(entity).despawn_recursive();
+    while let Some(message) = client.receive_message(ServerChannel::ServerEvents) {
+        let Ok(events) = bincode::deserialize::<Vec<ServerEvents>>(&message) else {
+            continue;
+        };
+        for event in events {
+            match event {
+                ServerEvents::OpenEvent(net) => {
+                    if let Some(event) = OpenEvent::from_net(net, &ids) {
+                        open_event.send(event);
+                    }
+                }
+                ServerEvents::DamageEvent(net) => {
+                    if let Some(event) = DamageEvent::from_net(net, &ids) {
+                        damage_event.send(event);
+                    }
+                }
+                ServerEvents::DeathEvent(net) => {
+                    if let Some(event) = DeathEvent::from_net(net, &ids) {
+                        death_event.send(event);
+                    }
+                }
+                // the channel isn't ordered, an older inventory may come in
+                // after a newer one
+                ServerEvents::Inventory(update) => {
+                    if update.tick < *inventory_tick {
+                        continue;
+                    }
+                    *inventory_tick = update.tick;
+                    *pending_inventory = Some(update.inventory);
+                }
+            }
         }
     }
-}
-
-pub fn tick(mut client: ResMut<RenetClient>, mut tick: ResMut<Tick>) {
-    if let Some(message) = client.receive_message(ServerChannel::Tick) {
-        let new_tick: Tick = bincode::deserialize(&message).unwrap();
-        tick.tick = new_tick.tick;
+    // the lifecycle channel may bring the player after its inventory
+    if let Ok(entity) = controlled.get_single() {
+        if let Some(inventory) = pending_inventory.take() {
+            commands.entity(entity).insert(inventory);
+        }
     }
 }
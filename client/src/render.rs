@@ -0,0 +1,333 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3, PI};
+
+use bevy::{gltf::Gltf, prelude::*};
+use bevy_easings::*;
+use bevy_mod_picking::prelude::*;
+use leafwing_input_manager::prelude::*;
+use lib::{
+    archetypes::Archetypes,
+    combat::DamageEvent,
+    components::{
+        Action, Arch, ControlledEntity, Door, EntityType, FloorTile, HealthBar, LeftClick,
+        OpenState, Tile,
+    },
+};
+
+use crate::{
+    action_bar::action_bar_input_map,
+    assets::ManAssetPack,
+    entities::{
+        archetype::spawn::PendingModel, player::control::PlayerBundle, wall::assets::WallAssetPack,
+    },
+    input::picking_listener,
+    SpawnWallEvent,
+};
+
+/// Eases entities towards their new tile and turns them to face the
+/// direction they moved in.
+pub fn move_to_tile(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Tile), Changed<Tile>>,
+) {
+    for (e, old_transform, t) in query.iter() {
+        let mut transform = t.to_transform();
+        let old = old_transform.translation;
+        let new = transform.translation;
+        if old == new {
+            continue;
+        }
+        let mut rotation = 0.;
+        if old.x > new.x {
+            rotation = -FRAC_PI_2;
+            //println!("WEST");
+        } else if old.x < new.x {
+            rotation = FRAC_PI_2;
+            //println!("EAST");
+        }
+        if old.z > new.z {
+            rotation = -PI;
+            //println!("NORTH");
+        } else if old.z < new.z {
+            rotation = 0.0;
+            //println!("SOUTH");
+        }
+
+        if old.x < new.x && old.z > new.z {
+            //println!("NORTH EAST");
+            rotation = 2.2;
+        }
+
+        if old.x > new.x && old.z > new.z {
+            rotation = -2.2;
+            //println!("NORTH WEST");
+        }
+
+        if old.x < new.x && old.z < new.z {
+            rotation = FRAC_PI_3;
+            //println!("SOUTH EAST");
+        }
+        if old.x > new.x && old.z < new.z {
+            //println!("SOUTH WEST");
+            rotation = -FRAC_PI_3;
+        }
+        transform.rotate_y(rotation);
+        commands.entity(e).insert(old_transform.ease_to(
+            transform,
+            bevy_easings::EaseFunction::QuadraticOut,
+            bevy_easings::EasingType::Once {
+                duration: std::time::Duration::from_millis(300),
+            },
+        ));
+    }
+}
+
+pub fn swing_door(mut query: Query<(&mut Transform, Ref<OpenState>)>) {
+    for (mut transform, open_state) in query.iter_mut() {
+        if !open_state.is_changed() || open_state.is_added() {
+            continue;
+        }
+        match *open_state {
+            OpenState::Open => {
+                transform.rotate_y(FRAC_PI_2);
+            }
+            OpenState::Closed => {
+                transform.rotate_y(-FRAC_PI_2);
+            }
+        }
+    }
+}
+
+/// Attaches the visuals of entities the ClientNetPlugin spawned.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn(
+    mut commands: Commands,
+    spawned: Query<(Entity, &EntityType, &Tile, Option<&ControlledEntity>), Added<EntityType>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    man_scene: Res<ManAssetPack>,
+    cube_scene: Res<WallAssetPack>,
+    assets: Res<Assets<Gltf>>,
+    archetypes: Res<Archetypes>,
+    mut spawn_wall_event: EventWriter<SpawnWallEvent>,
+) {
+    for (entity, entity_type, tile, controlled) in spawned.iter() {
+        match *entity_type {
+            EntityType::Tile => {
+                if let Some(gltf) = assets.get(&cube_scene.0) {
+                    commands.entity(entity).insert((
+                        SceneBundle {
+                            scene: gltf.named_scenes.get("Scene.001").unwrap().clone(),
+                            transform: tile.to_transform(),
+                            ..Default::default()
+                        },
+                        LeftClick::<Entity>::Walk,
+                        FloorTile,
+                        OnPointer::<Down>::run_callback(picking_listener),
+                    ));
+                } else {
+                    commands.entity(entity).insert((
+                        PbrBundle {
+                            mesh: meshes.add(Mesh::from(shape::Box::new(1., 0.2, 1.))),
+                            material: materials.add(Color::rgb(0.2, 0.5, 0.2).into()),
+                            transform: tile.to_transform(),
+                            ..Default::default()
+                        },
+                        LeftClick::<Entity>::Walk,
+                        FloorTile,
+                    ));
+                }
+            }
+            EntityType::Player(player) => {
+                println!("tile:{:?}", tile);
+                if let Some(gltf) = assets.get(&man_scene.0) {
+                    commands.entity(entity).insert((
+                        SceneBundle {
+                            scene: gltf.scenes[0].clone(),
+                            transform: tile.to_transform(),
+                            ..Default::default()
+                        },
+                        PlayerBundle::new(tile),
+                        player,
+                    ));
+                    let hp_bar = commands.spawn((HealthBar,)).id();
+                    commands.entity(entity).push_children(&[hp_bar]);
+                }
+
+                println!("spawn player: {:?}", player);
+                if controlled.is_some() {
+                    commands
+                        .entity(entity)
+                        .insert(InputManagerBundle::<Action> {
+                            action_state: ActionState::default(),
+                            input_map: action_bar_input_map(),
+                        });
+                }
+
+                commands.spawn(PointLightBundle {
+                    point_light: PointLight {
+                        intensity: 1500.0,
+                        shadows_enabled: true,
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(4.0, 8.0, 4.0),
+                    ..Default::default()
+                });
+            }
+            EntityType::Wall(wall) => {
+                spawn_wall_event.send(SpawnWallEvent { wall, tile: *tile });
+            }
+            EntityType::Arch(arch) => {
+                if let Some(gltf) = assets.get(&cube_scene.0) {
+                    let mut transform = tile.to_transform();
+                    if arch == Arch::Horizontal {
+                        transform.rotate_y(-FRAC_PI_2);
+                    }
+                    commands.entity(entity).insert(SceneBundle {
+                        scene: gltf.named_scenes.get("arch").unwrap().clone(),
+                        transform,
+                        ..Default::default()
+                    });
+                }
+            }
+            EntityType::Door(door) => {
+                if let Some(gltf) = assets.get(&cube_scene.0) {
+                    let mut transform = tile.to_transform();
+                    if door != Door::Vertical {
+                        transform.rotate_y(-FRAC_PI_2);
+                    }
+                    commands.entity(entity).insert((
+                        SceneBundle {
+                            scene: gltf.named_scenes.get("door").unwrap().clone(),
+                            transform,
+                            ..Default::default()
+                        },
+                        //insert floor tile for pathing
+                        FloorTile,
+                        LeftClick::Open(entity),
+                    ));
+                    if door == Door::Vertical {
+                        commands
+                            .entity(entity)
+                            .insert(OnPointer::<Down>::run_callback(picking_listener));
+                    }
+                }
+            }
+            EntityType::Lever(lever) => {
+                commands.entity(entity).insert((
+                    lever,
+                    PbrBundle {
+                        mesh: meshes.add(Mesh::from(shape::Box::new(1.0, 1.0, 1.0))),
+                        material: materials.add(Color::rgb(1.0, 0.2, 0.0).into()),
+                        transform: tile.to_transform(),
+                        ..Default::default()
+                    },
+                    LeftClick::<Entity>::Pull,
+                ));
+            }
+            EntityType::Archetype(id) => {
+                commands.entity(entity).insert(PendingModel);
+                let Some(archetype) = archetypes.get(id) else {
+                    continue;
+                };
+                if let Some(left_click) = archetype.click.left_click(entity) {
+                    commands.entity(entity).insert((
+                        left_click,
+                        OnPointer::<Down>::run_callback(picking_listener),
+                    ));
+                }
+                if archetype.stats.is_some() {
+                    let hp_bar = commands.spawn((HealthBar,)).id();
+                    commands.entity(entity).push_children(&[hp_bar]);
+                }
+            }
+            EntityType::Stairs(_) => {
+                commands.entity(entity).insert(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Box::new(0.8, 0.3, 0.8))),
+                    material: materials.add(Color::rgb(0.4, 0.3, 0.6).into()),
+                    transform: tile.to_transform(),
+                    ..Default::default()
+                });
+            }
+            EntityType::Item(_) => {
+                commands.entity(entity).insert((
+                    PbrBundle {
+                        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.3 })),
+                        material: materials.add(Color::rgb(0.9, 0.7, 0.1).into()),
+                        transform: tile.to_transform(),
+                        ..Default::default()
+                    },
+                    LeftClick::Pickup(Some(entity)),
+                    OnPointer::<Down>::run_callback(picking_listener),
+                ));
+            }
+        }
+    }
+}
+
+/// A damage number rising above where a hit landed, the UI node is moved
+/// to its screen position every frame.
+#[derive(Component)]
+pub struct DamageNumber {
+    pub position: Vec3,
+    pub timer: Timer,
+}
+
+pub fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut damage_event: EventReader<DamageEvent>,
+    tiles: Query<&Tile>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in damage_event.iter() {
+        let Ok(tile) = tiles.get(event.entity) else {
+            continue;
+        };
+        let (text, color) = match event.damage {
+            Some(damage) => (damage.to_string(), Color::RED),
+            None => ("miss".to_string(), Color::GRAY),
+        };
+        commands.spawn((
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 24.0,
+                    color,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            }),
+            DamageNumber {
+                position: tile.to_transform().translation + Vec3::Y * 2.0,
+                timer: Timer::from_seconds(1.0, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+pub fn float_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut numbers: Query<(Entity, &mut DamageNumber, &mut Style)>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    for (entity, mut number, mut style) in numbers.iter_mut() {
+        if number.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let position = number.position + Vec3::Y * number.timer.percent();
+        if let Some(screen) = camera.world_to_viewport(camera_transform, position) {
+            style.position = UiRect {
+                left: Val::Px(screen.x),
+                bottom: Val::Px(screen.y),
+                ..Default::default()
+            };
+        }
+    }
+}
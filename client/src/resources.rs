@@ -1,21 +1,24 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
 use bevy::utils::HashMap;
+use lib::{
+    net_id::NetId,
+    replication::{EntityState, Snapshot, SnapshotState},
+};
 
+/// `pending` holds the replicated components of entities whose spawn
+/// hasn't arrived yet, `tick` is the tick of the last lifecycle message.
+/// The entities themselves are mapped through `NetIds`.
 #[derive(Resource, Default)]
 pub struct NetworkMapping {
-    pub client: HashMap<Entity, Entity>,
-    pub server: HashMap<Entity, Entity>,
-}
-
-impl NetworkMapping {
-    pub fn add(&mut self, client_entity: &Entity, server_entity: &Entity) {
-        self.client.insert(*client_entity, *server_entity);
-        self.server.insert(*server_entity, *client_entity);
-    }
+    pub pending: HashMap<NetId, EntityState>,
+    pub tick: u64,
 }
 
 #[derive(Default)]
 pub struct ClientInfo {
+    pub name: String,
     pub client_entity: Option<Entity>,
     pub server_entity: Option<Entity>,
     pub controlled_entity: Option<Entity>,
@@ -25,3 +28,40 @@ pub struct ClientInfo {
 pub struct ClientLobby {
     pub clients: HashMap<u64, ClientInfo>,
 }
+
+/// Decoded snapshots that later deltas may still be based on, and the
+/// part of the latest one that has been applied to the world.
+#[derive(Resource, Default)]
+pub struct SnapshotBuffer {
+    pub latest: u64,
+    pub states: VecDeque<(u64, SnapshotState)>,
+    pub applied: SnapshotState,
+}
+
+impl SnapshotBuffer {
+    /// Decodes `snapshot` against its baseline, false if it is stale or
+    /// the baseline is gone.
+    pub fn receive(&mut self, snapshot: &Snapshot) -> bool {
+        if snapshot.tick <= self.latest {
+            return false;
+        }
+        let state = if snapshot.baseline == 0 {
+            snapshot.decode(&SnapshotState::default())
+        } else {
+            let Some((_, baseline)) = self
+                .states
+                .iter()
+                .find(|(tick, _)| *tick == snapshot.baseline)
+            else {
+                return false;
+            };
+            snapshot.decode(baseline)
+        };
+        self.latest = snapshot.tick;
+        // the server only deltas against acked snapshots and the acked
+        // tick never goes back, so nothing older is needed again
+        self.states.retain(|(tick, _)| *tick >= snapshot.baseline);
+        self.states.push_back((snapshot.tick, state));
+        true
+    }
+}
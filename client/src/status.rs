@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use lib::{
+    components::{HealthBar, Tile},
+    status::{StatusEffects, StatusKind},
+};
+
+/// The short label and color of a status, the color also tints the
+/// affected entity.
+fn icon(kind: StatusKind) -> (&'static str, Color) {
+    match kind {
+        StatusKind::Poison => ("PSN", Color::rgb(0.3, 0.9, 0.2)),
+        StatusKind::Regen => ("REG", Color::rgb(1.0, 0.5, 0.7)),
+        StatusKind::Slow => ("SLW", Color::rgb(0.3, 0.5, 1.0)),
+        StatusKind::Stun => ("STN", Color::rgb(1.0, 0.9, 0.2)),
+        StatusKind::Weaken => ("WKN", Color::rgb(0.6, 0.4, 0.8)),
+    }
+}
+
+/// The icon row that follows `0` around.
+#[derive(Component, Debug)]
+pub struct StatusIcons(pub Entity);
+
+/// Put on tinted meshes, the material to go back to once the effects are
+/// gone.
+#[derive(Component, Debug)]
+pub struct StatusTint {
+    pub original: Handle<StandardMaterial>,
+}
+
+/// Rebuilds the icons of entities whose effects changed, one label per
+/// effect with its stacks.
+pub fn update_status_icons(
+    mut commands: Commands,
+    changed: Query<(Entity, &StatusEffects), Changed<StatusEffects>>,
+    mut icons: Query<(&StatusIcons, &mut Text)>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, statuses) in changed.iter() {
+        let style = TextStyle {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 16.0,
+            color: Color::WHITE,
+        };
+        let sections: Vec<TextSection> = statuses
+            .effects
+            .iter()
+            .map(|effect| {
+                let (label, color) = icon(effect.kind);
+                let text = match effect.stacks {
+                    1 => format!("{label} "),
+                    stacks => format!("{label}x{stacks} "),
+                };
+                TextSection::new(
+                    text,
+                    TextStyle {
+                        color,
+                        ..style.clone()
+                    },
+                )
+            })
+            .collect();
+        match icons.iter_mut().find(|(icons, _)| icons.0 == entity) {
+            Some((_, mut text)) => text.sections = sections,
+            None => {
+                commands.spawn((
+                    TextBundle::from_sections(sections).with_style(Style {
+                        position_type: PositionType::Absolute,
+                        ..Default::default()
+                    }),
+                    StatusIcons(entity),
+                ));
+            }
+        }
+    }
+}
+
+/// Keeps the icons above their entity, despawning them with it.
+pub fn follow_status_icons(
+    mut commands: Commands,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    tiles: Query<&Tile>,
+    mut icons: Query<(Entity, &StatusIcons, &mut Style)>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    for (entity, icons, mut style) in icons.iter_mut() {
+        let Ok(tile) = tiles.get(icons.0) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        let position = tile.to_transform().translation + Vec3::Y * 2.5;
+        if let Some(screen) = camera.world_to_viewport(camera_transform, position) {
+            style.position = UiRect {
+                left: Val::Px(screen.x),
+                bottom: Val::Px(screen.y),
+                ..Default::default()
+            };
+        }
+    }
+}
+
+/// Tints the meshes of entities in the color of their first effect and
+/// puts the original materials back once it's gone.
+pub fn tint_status(
+    mut commands: Commands,
+    changed: Query<(Entity, &StatusEffects), Changed<StatusEffects>>,
+    children: Query<&Children>,
+    mut meshes: Query<(&mut Handle<StandardMaterial>, Option<&StatusTint>), Without<HealthBar>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, statuses) in changed.iter() {
+        let tint = statuses.effects.first().map(|effect| icon(effect.kind).1);
+        for mesh in children.iter_descendants(entity) {
+            let Ok((mut handle, tinted)) = meshes.get_mut(mesh) else {
+                continue;
+            };
+            let original = tinted.map_or(handle.clone(), |tinted| tinted.original.clone());
+            let Some(tint) = tint else {
+                *handle = original;
+                commands.entity(mesh).remove::<StatusTint>();
+                continue;
+            };
+            let Some(mut material) = materials.get(&original).cloned() else {
+                continue;
+            };
+            material.base_color = Color::from(Vec4::from(material.base_color) * Vec4::from(tint));
+            *handle = materials.add(material);
+            commands.entity(mesh).insert(StatusTint { original });
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{combat::AttackRange, resources::Tick, status::StatusKind};
+
+/// Read at startup when it exists, like the archetypes file.
+pub const ABILITIES_FILE: &str = "lib/data/abilities.ron";
+
+/// The position of an ability in `data/abilities.ron`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct AbilityId(pub u16);
+
+impl AbilityId {
+    pub const AUTO_ATTACK: AbilityId = AbilityId(0);
+}
+
+/// Who an ability is used on.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AbilityTarget {
+    #[default]
+    Enemy,
+    Caster,
+}
+
+/// What an ability does to its target once it goes off.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    /// A hit rolled from the caster's and target's Stats.
+    Attack,
+    /// This much damage, no roll.
+    Damage(u16),
+    Heal(u16),
+    /// A stack of the status for this many ticks.
+    Status(StatusKind, u64),
+    /// Puts the caster this much threat above the top of the target's
+    /// threat table, mobs go after whoever has the most.
+    Taunt(u32),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ability {
+    pub name: String,
+    /// Ticks between starting the cast and the effects, 0 is instant.
+    pub cast_ticks: u64,
+    pub cooldown_ticks: u64,
+    pub range: AttackRange,
+    pub target: AbilityTarget,
+    /// Mana it takes to cast.
+    pub cost: u16,
+    pub effects: Vec<Effect>,
+}
+
+/// Every ability, client and server load the same file so the ids agree.
+#[derive(Resource, Debug)]
+pub struct Abilities {
+    abilities: Vec<Ability>,
+}
+
+impl Abilities {
+    pub fn from_ron(ron: &str) -> Result<Self, String> {
+        let abilities = ron::from_str(ron).map_err(|err| format!("parsing abilities: {err}"))?;
+        Ok(Self { abilities })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let ron = std::fs::read_to_string(path)
+            .map_err(|err| format!("reading {}: {err}", path.display()))?;
+        Self::from_ron(&ron)
+    }
+
+    /// ABILITIES_FILE if it's there, the copy built into the binary if not.
+    pub fn load() -> Result<Self, String> {
+        if Path::new(ABILITIES_FILE).exists() {
+            return Self::from_file(ABILITIES_FILE);
+        }
+        Self::from_ron(include_str!("../data/abilities.ron"))
+    }
+
+    pub fn get(&self, id: AbilityId) -> Option<&Ability> {
+        self.abilities.get(id.0 as usize)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (AbilityId, &Ability)> {
+        self.abilities
+            .iter()
+            .enumerate()
+            .map(|(id, ability)| (AbilityId(id as u16), ability))
+    }
+}
+
+/// The copy built into the binary, `load` reads ABILITIES_FILE.
+impl Default for Abilities {
+    fn default() -> Self {
+        Self::from_ron(include_str!("../data/abilities.ron")).unwrap()
+    }
+}
+
+/// The tick each ability an entity used is ready again.
+#[derive(Default, Component, Debug)]
+pub struct CoolDowns {
+    pub ready_at: HashMap<AbilityId, u64>,
+}
+
+impl CoolDowns {
+    pub fn is_ready(&self, ability: AbilityId, tick: &Tick) -> bool {
+        self.ready_at
+            .get(&ability)
+            .map_or(true, |ready_at| *ready_at <= tick.tick)
+    }
+
+    pub fn start(&mut self, ability: AbilityId, cooldown_ticks: u64, tick: &Tick) {
+        self.ready_at.insert(ability, tick.tick + cooldown_ticks);
+    }
+}
@@ -0,0 +1,184 @@
+//! Mobs and props as data. Each archetype in `data/archetypes.ron` says
+//! what an entity looks like, whether it fights, blocks its tile or thinks,
+//! so a new monster or prop is an entry there instead of an EntityType
+//! variant. Entities carry the numeric `ArchetypeId` on the wire, data
+//! files refer to archetypes by name.
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::Stats,
+    components::{EntityType, LeftClick, Untraversable},
+};
+
+/// Read at startup when it exists, so content changes need no rebuild.
+/// Client and server have to load the same file for the ids to agree.
+pub const ARCHETYPES_FILE: &str = "lib/data/archetypes.ron";
+
+/// The position of an archetype in the archetypes file.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct ArchetypeId(pub u16);
+
+/// How clients draw an archetype.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Model {
+    /// A scene of a GLTF file, looping `idle` if it has animations.
+    Scene {
+        gltf: String,
+        scene: String,
+        idle: Option<usize>,
+    },
+    Capsule((f32, f32, f32)),
+    Cube(f32, (f32, f32, f32)),
+}
+
+/// What clicking the entity does.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Click {
+    #[default]
+    Nothing,
+    Attack,
+    Open,
+}
+
+impl Click {
+    pub fn left_click(&self, entity: Entity) -> Option<LeftClick> {
+        match self {
+            Click::Nothing => None,
+            Click::Attack => Some(LeftClick::Attack(entity)),
+            Click::Open => Some(LeftClick::Open(entity)),
+        }
+    }
+}
+
+/// How a mob thinks, see `server/src/ai.rs`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct AiProfile {
+    pub aggro_radius: u32,
+    /// Ticks between steps when nothing slows it.
+    pub step_ticks: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Archetype {
+    pub name: String,
+    pub model: Option<Model>,
+    /// Stats at level 1, None for things that can't be fought.
+    pub stats: Option<Stats>,
+    /// Added to `stats` for every level past the first.
+    pub per_level: Stats,
+    /// Heals up instead of dying.
+    pub immortal: bool,
+    /// Blocks its tile for pathing.
+    pub blocks: bool,
+    pub click: Click,
+    pub ai: Option<AiProfile>,
+}
+
+impl Default for Archetype {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            model: None,
+            stats: None,
+            per_level: Stats::default(),
+            immortal: false,
+            blocks: false,
+            click: Click::Nothing,
+            ai: None,
+        }
+    }
+}
+
+impl Archetype {
+    /// Stats at `level`, None for things that can't be fought.
+    pub fn stats(&self, level: u16) -> Option<Stats> {
+        let extra = level.saturating_sub(1);
+        let grow = |base: u16, per_level: u16| base.saturating_add(per_level.saturating_mul(extra));
+        self.stats.map(|stats| Stats {
+            attack: grow(stats.attack, self.per_level.attack),
+            defense: grow(stats.defense, self.per_level.defense),
+            max_hp: grow(stats.max_hp, self.per_level.max_hp),
+        })
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Archetypes {
+    archetypes: Vec<Archetype>,
+}
+
+impl Archetypes {
+    pub fn from_ron(ron: &str) -> Result<Self, String> {
+        let archetypes = ron::from_str(ron).map_err(|err| format!("parsing archetypes: {err}"))?;
+        Ok(Self { archetypes })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let ron = std::fs::read_to_string(path)
+            .map_err(|err| format!("reading {}: {err}", path.display()))?;
+        Self::from_ron(&ron)
+    }
+
+    /// ARCHETYPES_FILE if it's there, the copy built into the binary if not.
+    pub fn load() -> Result<Self, String> {
+        if Path::new(ARCHETYPES_FILE).exists() {
+            return Self::from_file(ARCHETYPES_FILE);
+        }
+        Self::from_ron(include_str!("../data/archetypes.ron"))
+    }
+
+    pub fn get(&self, id: ArchetypeId) -> Option<&Archetype> {
+        self.archetypes.get(id.0 as usize)
+    }
+
+    pub fn id(&self, name: &str) -> Option<ArchetypeId> {
+        self.iter()
+            .find(|(_, archetype)| archetype.name == name)
+            .map(|(id, _)| id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ArchetypeId, &Archetype)> {
+        self.archetypes
+            .iter()
+            .enumerate()
+            .map(|(id, archetype)| (ArchetypeId(id as u16), archetype))
+    }
+
+    /// The archetype of an entity, None for the built in entity types.
+    pub fn of(&self, entity_type: &EntityType) -> Option<&Archetype> {
+        match entity_type {
+            EntityType::Archetype(id) => self.get(*id),
+            _ => None,
+        }
+    }
+}
+
+/// The copy built into the binary, `load` reads ARCHETYPES_FILE.
+impl Default for Archetypes {
+    fn default() -> Self {
+        Self::from_ron(include_str!("../data/archetypes.ron")).unwrap()
+    }
+}
+
+/// Marks archetypes that block their tile, on both sides so the client
+/// predicts the same paths the server walks.
+pub fn mark_blockers(
+    mut commands: Commands,
+    spawned: Query<(Entity, &EntityType), Added<EntityType>>,
+    archetypes: Res<Archetypes>,
+) {
+    for (entity, entity_type) in spawned.iter() {
+        if archetypes
+            .of(entity_type)
+            .is_some_and(|archetype| archetype.blocks)
+        {
+            commands.entity(entity).insert(Untraversable);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use bevy_renet::renet::{ConnectToken, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES};
+use serde::{Deserialize, Serialize};
+
+use crate::config::NetConfig;
+
+/// Who a connection belongs to. Travels in the renet user data: the account
+/// id followed by the length prefixed display name.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UserData {
+    pub account_id: u64,
+    pub name: String,
+}
+
+impl UserData {
+    pub fn to_bytes(&self) -> [u8; NETCODE_USER_DATA_BYTES] {
+        let mut bytes = [0; NETCODE_USER_DATA_BYTES];
+        bytes[..8].copy_from_slice(&self.account_id.to_le_bytes());
+        let name = self.name.as_bytes();
+        let len = name.len().min(NETCODE_USER_DATA_BYTES - 9);
+        bytes[8] = len as u8;
+        bytes[9..9 + len].copy_from_slice(&name[..len]);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; NETCODE_USER_DATA_BYTES]) -> Self {
+        let account_id = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let len = (bytes[8] as usize).min(NETCODE_USER_DATA_BYTES - 9);
+        let name = String::from_utf8_lossy(&bytes[9..9 + len]).into_owned();
+        Self { account_id, name }
+    }
+}
+
+/// Sent by a client to the auth service, which answers with a
+/// `TokenResponse`. The first login with a name sets its password.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenRequest {
+    pub name: String,
+    pub password: String,
+    pub protocol_id: u64,
+}
+
+/// A written ConnectToken, or why there is none.
+pub type TokenResponse = Result<Vec<u8>, String>;
+
+/// Asks the auth service from `config` for a connect token.
+pub fn request_token(config: &NetConfig) -> Result<ConnectToken, String> {
+    let password = config
+        .password
+        .clone()
+        .ok_or("no password configured, secure mode logs in with one")?;
+    let auth_addr = SocketAddr::new(config.public_addr, config.auth_port);
+    let mut stream = TcpStream::connect_timeout(&auth_addr, Duration::from_secs(5))
+        .map_err(|err| format!("connecting to auth at {auth_addr}: {err}"))?;
+    let request = TokenRequest {
+        name: config.player_name.clone(),
+        password,
+        protocol_id: config.protocol_id,
+    };
+    stream
+        .write_all(&bincode::serialize(&request).unwrap())
+        .and_then(|_| stream.shutdown(Shutdown::Write))
+        .map_err(|err| format!("sending token request: {err}"))?;
+    let mut response = vec![];
+    stream
+        .read_to_end(&mut response)
+        .map_err(|err| format!("reading token response: {err}"))?;
+    let token = bincode::deserialize::<TokenResponse>(&response)
+        .map_err(|err| format!("bad token response: {err}"))??;
+    ConnectToken::read(&mut token.as_slice()).map_err(|err| format!("bad connect token: {err}"))
+}
+
+pub fn key_to_hex(key: &[u8; NETCODE_KEY_BYTES]) -> String {
+    key.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn key_from_hex(hex: &str) -> Result<[u8; NETCODE_KEY_BYTES], String> {
+    if hex.len() != NETCODE_KEY_BYTES * 2 || !hex.is_ascii() {
+        return Err(format!(
+            "private key must be {} hex digits",
+            NETCODE_KEY_BYTES * 2
+        ));
+    }
+    let mut key = [0; NETCODE_KEY_BYTES];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|err| format!("private key: {err}"))?;
+    }
+    Ok(key)
+}
@@ -1,12 +1,11 @@
-use bevy_renet::renet::{
-    ChannelConfig, ChunkChannelConfig, ReliableChannelConfig, UnreliableChannelConfig,
-};
+use bevy_renet::renet::{ChannelConfig, ReliableChannelConfig, UnreliableChannelConfig};
 use std::time::Duration;
 
 pub enum ClientChannel {
     Command,
     Input,
     Click,
+    SnapshotAck,
 }
 
 impl From<ClientChannel> for u8 {
@@ -15,6 +14,7 @@ impl From<ClientChannel> for u8 {
             ClientChannel::Command => 0,
             ClientChannel::Input => 1,
             ClientChannel::Click => 2,
+            ClientChannel::SnapshotAck => 3,
         }
     }
 }
@@ -41,32 +41,40 @@ impl ClientChannel {
                 ..Default::default()
             }
             .into(),
+            UnreliableChannelConfig {
+                channel_id: Self::SnapshotAck.into(),
+                sequenced: true,
+                ..Default::default()
+            }
+            .into(),
         ]
     }
 }
 
+/// Upper bound of a snapshot message, renet packets are at most 16kb and
+/// anything above max_message_size errors the channel.
+pub const SNAPSHOT_BUDGET: u64 = 10 * 1024;
+
 pub enum ServerChannel {
-    Spawn,
-    Despawn,
+    Lifecycle,
     Update,
-    Load,
     ServerMessages,
     Tick,
     Test,
     ServerEvents,
+    InputAck,
 }
 
 impl From<ServerChannel> for u8 {
     fn from(channel_id: ServerChannel) -> Self {
         match channel_id {
-            ServerChannel::Spawn => 0,
-            ServerChannel::Despawn => 1,
+            ServerChannel::Lifecycle => 0,
             ServerChannel::Update => 2,
-            ServerChannel::Load => 3,
             ServerChannel::ServerMessages => 4,
             ServerChannel::Tick => 5,
             ServerChannel::Test => 6,
             ServerChannel::ServerEvents => 7,
+            ServerChannel::InputAck => 8,
         }
     }
 }
@@ -76,28 +84,17 @@ impl ServerChannel {
     pub fn channels_config() -> Vec<ChannelConfig> {
         vec![
             ReliableChannelConfig {
-                channel_id: Self::Spawn.into(),
-                message_resend_time: Duration::from_millis(200),
-                ..Default::default()
-            }
-            .into(),
-            ReliableChannelConfig {
-                channel_id: Self::Despawn.into(),
+                channel_id: Self::Lifecycle.into(),
                 message_resend_time: Duration::from_millis(200),
+                ordered: true,
                 ..Default::default()
             }
             .into(),
             UnreliableChannelConfig {
                 channel_id: Self::Update.into(),
-                sequenced: false,
-                ..Default::default() //ReliableChannelConfig {
-                                     //channel_id: Self::Update.into(),
-                                     //message_resend_time: Duration::from_millis(200),
-                                     //..Default::default()
-            }
-            .into(),
-            ChunkChannelConfig {
-                channel_id: Self::Load.into(),
+                sequenced: true,
+                packet_budget: SNAPSHOT_BUDGET,
+                max_message_size: SNAPSHOT_BUDGET,
                 ..Default::default()
             }
             .into(),
@@ -125,6 +122,12 @@ impl ServerChannel {
                 ..Default::default()
             }
             .into(),
+            UnreliableChannelConfig {
+                channel_id: Self::InputAck.into(),
+                sequenced: true,
+                ..Default::default()
+            }
+            .into(),
         ]
     }
 }
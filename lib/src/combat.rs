@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::Tile,
+    nav::{is_adjacent, NavGrid},
+    net_id::{NetId, NetIds, NetMapped},
+};
+
+/// Combat stats of anything that fights, replicated so clients can show
+/// health bars against max_hp.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, Component)]
+pub struct Stats {
+    pub attack: u16,
+    pub defense: u16,
+    pub max_hp: u16,
+}
+
+impl Stats {
+    pub fn new(attack: u16, defense: u16, max_hp: u16) -> Self {
+        Self {
+            attack,
+            defense,
+            max_hp,
+        }
+    }
+
+    /// Chance to land a hit on `defender`, between 5% and 95%.
+    pub fn hit_chance(&self, defender: &Stats) -> f32 {
+        let attack = self.attack as f32 + 1.;
+        let defense = defender.defense as f32 + 1.;
+        (attack / (attack + defense)).clamp(0.05, 0.95)
+    }
+
+    /// Highest damage a hit on `defender` can do, half the defense is
+    /// taken off the attack.
+    pub fn max_hit(&self, defender: &Stats) -> u16 {
+        self.attack.saturating_sub(defender.defense / 2).max(1)
+    }
+}
+
+/// What abilities cost, the server regenerates it over time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
+pub struct Mana {
+    pub mp: u16,
+    pub max: u16,
+}
+
+impl Mana {
+    pub fn new(max: u16) -> Self {
+        Self { mp: max, max }
+    }
+}
+
+/// How far an ability reaches.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AttackRange {
+    /// One of the 8 tiles around the attacker.
+    #[default]
+    Melee,
+    /// Up to this many tiles away with nothing Untraversable in between.
+    Ranged(u32),
+}
+
+impl AttackRange {
+    /// Whether an attack from `from` can hit something on `to`.
+    pub fn reaches(&self, from: &Tile, to: &Tile, grid: &NavGrid) -> bool {
+        match self {
+            AttackRange::Melee => is_adjacent(from, to),
+            AttackRange::Ranged(range) => {
+                let dx = from.cell.0.abs_diff(to.cell.0);
+                let dz = from.cell.2.abs_diff(to.cell.2);
+                from != to && dx.max(dz) <= *range && grid.line_of_sight(from, to)
+            }
+        }
+    }
+
+    /// Steps to the closest tile `target` is in reach from, empty if it
+    /// already is.
+    pub fn path_into_range(&self, grid: &NavGrid, start: Tile, target: Tile) -> Option<Vec<Tile>> {
+        match self {
+            AttackRange::Melee => grid.find_path(start, target, true),
+            AttackRange::Ranged(_) => {
+                grid.find_path_where(start, target, |tile| self.reaches(tile, &target, grid))
+            }
+        }
+    }
+}
+
+/// An attack that landed or missed, `damage` is None for a miss. Sent to
+/// every client that sees `entity` so it can show the number.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct DamageEvent<E = Entity> {
+    pub entity: E,
+    pub attacker: E,
+    pub damage: Option<u16>,
+}
+
+/// `entity` ran out of health. Mobs are despawned, players respawn.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct DeathEvent<E = Entity> {
+    pub entity: E,
+}
+
+impl NetMapped for DamageEvent {
+    type Net = DamageEvent<NetId>;
+
+    fn to_net(&self, ids: &NetIds) -> Option<Self::Net> {
+        Some(DamageEvent {
+            entity: ids.net_id(self.entity)?,
+            attacker: ids.net_id(self.attacker)?,
+            damage: self.damage,
+        })
+    }
+
+    fn from_net(net: Self::Net, ids: &NetIds) -> Option<Self> {
+        Some(DamageEvent {
+            entity: ids.entity(net.entity)?,
+            attacker: ids.entity(net.attacker)?,
+            damage: net.damage,
+        })
+    }
+}
+
+impl NetMapped for DeathEvent {
+    type Net = DeathEvent<NetId>;
+
+    fn to_net(&self, ids: &NetIds) -> Option<Self::Net> {
+        Some(DeathEvent {
+            entity: ids.net_id(self.entity)?,
+        })
+    }
+
+    fn from_net(net: Self::Net, ids: &NetIds) -> Option<Self> {
+        Some(DeathEvent {
+            entity: ids.entity(net.entity)?,
+        })
+    }
+}
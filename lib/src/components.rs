@@ -2,17 +2,48 @@ use bevy::{prelude::*, utils::HashSet};
 use leafwing_input_manager::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::resources::Tick;
+use crate::{
+    abilities::AbilityId, archetypes::ArchetypeId, combat::AttackRange, equipment::EquipSlot,
+    items::ItemId, net_id::NetId, resources::Tick,
+};
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Component)]
 pub struct Open;
 
-#[derive(Debug, Serialize, Deserialize, Component)]
-pub enum PlayerCommand {
-    LeftClick(LeftClick, Tile),
-    AutoAttack,
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, Component)]
+pub enum PlayerCommand<E = Entity> {
+    LeftClick(LeftClick<E>, Tile),
+    /// `None` uses it on the current Target, or the caster for abilities
+    /// that target it.
+    UseAbility(AbilityId, Option<E>),
+    /// Drops the stack in this inventory slot where the player stands.
+    DropItem(u8),
+    /// Moves the stack in the first slot onto the second.
+    MoveItem(u8, u8),
+    /// Splits this many off the stack in the slot into an empty one.
+    SplitStack(u8, u16),
+    /// Equips the item in this inventory slot.
+    Equip(u8),
+    /// Puts what's in the equipment slot back into the inventory.
+    Unequip(EquipSlot),
     //RunTo(Tile, Path),
 }
+/// A command tagged with the client's input sequence, the server echoes
+/// the last one it processed back in an `InputAck`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlayerInput {
+    pub sequence: u64,
+    pub command: PlayerCommand<NetId>,
+}
+
+/// The authoritative tile of the controlled entity after the server walked
+/// `step` tiles of the path it planned for input `sequence`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct InputAck {
+    pub sequence: u64,
+    pub step: u32,
+    pub tile: Tile,
+}
 #[derive(Debug)]
 pub enum Direction {
     Bad,
@@ -41,6 +72,8 @@ pub struct Path {
     pub destination: Tile,
     pub origin: Tile,
     pub left_click: LeftClick,
+    /// How close a click on an entity has to get to it.
+    pub range: AttackRange,
 }
 
 impl Path {
@@ -124,13 +157,15 @@ pub struct Untraversable;
 pub enum EntityType {
     Tile,
     Player(Player),
-    Sword(Sword),
     Wall(Wall),
     Door(Door),
     Arch(Arch),
     Lever(Lever),
-    Dummy(Dummy),
-    Slime(Slime),
+    /// A mob or prop defined in `data/archetypes.ron`.
+    Archetype(ArchetypeId),
+    Stairs(Stairs),
+    /// A stack of the item lying on the ground.
+    Item(ItemId),
 }
 #[derive(Component)]
 pub struct ControlledEntity;
@@ -141,32 +176,52 @@ pub enum OpenState {
     Closed,
     Open,
 }
-#[derive(Eq, PartialEq, Copy, Clone, Default, Debug, Serialize, Deserialize, Component)]
-pub enum LeftClick {
-    #[default]
+/// What a click does. `E` is how the clicked entity is referenced, an
+/// Entity in either world and a NetId on the wire.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize, Component)]
+pub enum LeftClick<E = Entity> {
     Walk,
-    Attack(Entity),
-    Pickup(Option<Entity>),
+    Attack(E),
+    Pickup(Option<E>),
     Pull,
-    Open(Entity),
-    Close(Entity),
+    Open(E),
+    Close(E),
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Component)]
-pub enum ComponentType {
-    Tile(Tile),
-    Player(Player),
-    Open(Open),
-    Health(Health),
-    Running(Running),
-    Target(Target),
-    CombatState(CombatState),
-    OpenState(OpenState),
+// a derive would require E: Default
+#[allow(clippy::derivable_impls)]
+impl<E> Default for LeftClick<E> {
+    fn default() -> Self {
+        LeftClick::Walk
+    }
+}
+
+impl<E> LeftClick<E> {
+    /// Swaps the referenced entity, None if `f` can't.
+    pub fn map<T>(self, mut f: impl FnMut(E) -> Option<T>) -> Option<LeftClick<T>> {
+        Some(match self {
+            LeftClick::Walk => LeftClick::Walk,
+            LeftClick::Attack(e) => LeftClick::Attack(f(e)?),
+            LeftClick::Pickup(Some(e)) => LeftClick::Pickup(Some(f(e)?)),
+            LeftClick::Pickup(None) => LeftClick::Pickup(None),
+            LeftClick::Pull => LeftClick::Pull,
+            LeftClick::Open(e) => LeftClick::Open(f(e)?),
+            LeftClick::Close(e) => LeftClick::Close(f(e)?),
+        })
+    }
+}
+
+/// A replicated component on the wire, `id` is its index in the
+/// `ReplicationRegistry` and `data` the bincode encoded component.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComponentType {
+    pub id: u16,
+    pub data: Vec<u8>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Component)]
 pub enum ServerMessages {
-    PlayerConnected { id: u64 },
+    PlayerConnected { id: u64, name: String },
     PlayerDisconnected { id: u64 },
 }
 
@@ -276,7 +331,14 @@ impl Scope {
         let br_x = self.bottom_right.cell.0;
         let br_z = self.bottom_right.cell.2;
 
-        x <= tl_x && x >= br_x && z <= tl_z && z >= br_z
+        let y = pos.cell.1;
+
+        x <= tl_x
+            && x >= br_x
+            && z <= tl_z
+            && z >= br_z
+            && y >= self.down.cell.1
+            && y <= self.up.cell.1
     }
 }
 
@@ -285,11 +347,19 @@ pub struct Player {
     pub id: u64,
 }
 
-pub enum SyncEvent {
-    Spawn(u64, SpawnEvent),
-    Despawn(u64, DespawnEvent),
-    Update(u64, UpdateEvent),
-    Remove(u64, RemoveEvent),
+/// A change to the set of entities a client can see.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum LifecycleEvent {
+    Spawn(NetId, EntityType, Tile),
+    Despawn(NetId),
+}
+
+/// The lifecycle events of one tick, split over several messages when there
+/// are many. They arrive in order on ServerChannel::Lifecycle.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LifecycleMessage {
+    pub tick: u64,
+    pub events: Vec<LifecycleEvent>,
 }
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
 pub struct SpawnEvent {
@@ -315,15 +385,7 @@ pub struct RemoveEvent {
     pub entity: Entity,
     pub component: ComponentType,
 }
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateEvent {
-    pub entity: Entity,
-    pub component: ComponentType,
-}
-pub struct TickEvent(Tick);
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
-pub struct Sword;
+pub struct TickEvent(pub Tick);
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
 pub enum Wall {
@@ -336,17 +398,46 @@ pub enum Arch {
     Horizontal,
     Vertical,
 }
+impl Arch {
+    /// An arch spans three tiles from its own tile, the two outer ones are
+    /// pillars and the middle one is the passage a door sits in.
+    pub fn pillars(&self, tile: Tile) -> [Tile; 2] {
+        let mut far = tile;
+        match self {
+            Arch::Horizontal => far.cell.2 += 2,
+            Arch::Vertical => far.cell.0 += 2,
+        }
+        [tile, far]
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
 pub enum Door {
     Horizontal,
     Vertical,
 }
 
+impl Door {
+    /// The tile a closed door blocks, doors share their tile with the arch
+    /// they are hung in.
+    pub fn passage(&self, tile: Tile) -> Tile {
+        let mut passage = tile;
+        match self {
+            Door::Horizontal => passage.cell.2 += 1,
+            Door::Vertical => passage.cell.0 += 1,
+        }
+        passage
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
 pub struct Lever;
 
+/// Stairs to another floor, players stepping on them arrive on `to`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
-pub struct Dummy;
+pub struct Stairs {
+    pub to: Tile,
+}
 
 #[derive(
     Default, Reflect, Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component,
@@ -362,27 +453,23 @@ impl Health {
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Component)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Component)]
 pub struct Target(pub Option<Entity>);
 
+/// The action bar slots, slot n uses the ability with id n.
 #[derive(Reflect, Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub enum Action {
-    AutoAttack,
-}
-
-#[derive(Default, Component)]
-pub struct CoolDowns {
-    pub auto_attack: u64,
+    Bar1,
+    Bar2,
+    Bar3,
+    Bar4,
+    Bar5,
+    Bar6,
 }
 
-impl CoolDowns {
-    pub fn cd_auto_attack(&mut self, tick: &Tick) -> bool {
-        if self.auto_attack <= tick.tick {
-            self.auto_attack = tick.tick + 24;
-            true
-        } else {
-            false
-        }
+impl Action {
+    pub fn ability(&self) -> AbilityId {
+        AbilityId(*self as u16)
     }
 }
 
@@ -394,6 +481,3 @@ pub enum CombatState {
 
 #[derive(Component)]
 pub struct FloorTile;
-
-#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize, Component)]
-pub struct Slime;
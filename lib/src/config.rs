@@ -0,0 +1,180 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::NETCODE_KEY_BYTES;
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::key_from_hex, PROTOCOL_ID};
+
+/// Read when no `--config` is given and the file exists.
+pub const DEFAULT_CONFIG: &str = "dg.ron";
+
+/// Read for the password so it stays out of `ps` and the shell history.
+pub const PASSWORD_VAR: &str = "DG_PASSWORD";
+
+/// Network settings of both binaries. Loaded from a RON file, any field
+/// can be left out, then overridden from the command line.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetConfig {
+    /// Address the server's socket binds to.
+    pub bind_addr: IpAddr,
+    /// Address clients connect to, behind NAT this differs from bind_addr.
+    pub public_addr: IpAddr,
+    pub port: u16,
+    pub max_clients: usize,
+    /// Length of a server tick in milliseconds.
+    pub tick_ms: u64,
+    pub protocol_id: u64,
+    pub player_name: String,
+    /// Logs player_name in with the auth service in secure mode.
+    pub password: Option<String>,
+    /// Only accept connect tokens from the auth service.
+    pub secure: bool,
+    /// Port of the auth service on public_addr.
+    pub auth_port: u16,
+    /// Hex encoded key shared by the server and the auth service.
+    pub private_key: Option<String>,
+    /// Generate the world from this seed instead of loading the map file.
+    pub dungeon_seed: Option<u64>,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            public_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 5000,
+            max_clients: 64,
+            tick_ms: 100,
+            protocol_id: PROTOCOL_ID,
+            player_name: "player".to_string(),
+            password: None,
+            secure: false,
+            auth_port: 5100,
+            private_key: None,
+            dungeon_seed: None,
+        }
+    }
+}
+
+const USAGE: &str = "options:
+    --config <file>        RON config file, defaults to dg.ron
+    --bind <ip>            address the server binds to
+    --public-addr <ip>     address clients connect to
+    --port <port>
+    --max-clients <n>
+    --tick-ms <ms>         length of a tick
+    --protocol-id <id>
+    --name <player name>
+    --password <password>  the first login with a name sets it, visible to
+                           other users, prefer setting DG_PASSWORD
+    --secure               connect through the auth service
+    --auth-port <port>
+    --private-key <hex>    key shared by the server and auth
+    --dungeon <seed>       generate the world, `random` for a new seed";
+
+impl NetConfig {
+    /// The config file followed by the command line of this process.
+    pub fn load() -> Result<Self, String> {
+        Self::from_args(std::env::args().skip(1))
+    }
+
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.into_iter().collect();
+        let mut config = match args.iter().position(|arg| arg == "--config") {
+            Some(index) => {
+                let path = args
+                    .get(index + 1)
+                    .ok_or_else(|| format!("--config needs a value\n{USAGE}"))?;
+                Self::from_file(Path::new(path))?
+            }
+            None if Path::new(DEFAULT_CONFIG).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG))?
+            }
+            None => Self::default(),
+        };
+        if let Ok(password) = std::env::var(PASSWORD_VAR) {
+            config.password = Some(password);
+        }
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            if flag == "--help" || flag == "-h" {
+                return Err(USAGE.to_string());
+            }
+            if flag == "--secure" {
+                config.secure = true;
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{flag} needs a value\n{USAGE}"))?;
+            config.set(&flag, &value)?;
+        }
+        if config.tick_ms == 0 {
+            return Err(format!("tick_ms has to be at least 1\n{USAGE}"));
+        }
+        if config.max_clients == 0 {
+            return Err(format!("max_clients has to be at least 1\n{USAGE}"));
+        }
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let file = std::fs::read_to_string(path)
+            .map_err(|err| format!("reading {}: {err}", path.display()))?;
+        ron::from_str(&file).map_err(|err| format!("parsing {}: {err}", path.display()))
+    }
+
+    fn set(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        let invalid = |err: &dyn std::fmt::Display| format!("{flag} {value}: {err}");
+        match flag {
+            "--config" => (),
+            "--bind" => self.bind_addr = value.parse().map_err(|err| invalid(&err))?,
+            "--public-addr" => self.public_addr = value.parse().map_err(|err| invalid(&err))?,
+            "--port" => self.port = value.parse().map_err(|err| invalid(&err))?,
+            "--max-clients" => self.max_clients = value.parse().map_err(|err| invalid(&err))?,
+            "--tick-ms" => self.tick_ms = value.parse().map_err(|err| invalid(&err))?,
+            "--protocol-id" => self.protocol_id = value.parse().map_err(|err| invalid(&err))?,
+            "--name" => self.player_name = value.to_string(),
+            "--password" => self.password = Some(value.to_string()),
+            "--auth-port" => self.auth_port = value.parse().map_err(|err| invalid(&err))?,
+            "--private-key" => self.private_key = Some(value.to_string()),
+            "--dungeon" if value == "random" => self.dungeon_seed = Some(random_seed()),
+            "--dungeon" => self.dungeon_seed = Some(value.parse().map_err(|err| invalid(&err))?),
+            _ => return Err(format!("unknown option {flag}\n{USAGE}")),
+        }
+        Ok(())
+    }
+
+    pub fn bind_socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_addr, self.port)
+    }
+
+    pub fn public_socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.public_addr, self.port)
+    }
+
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick_ms)
+    }
+
+    pub fn private_key(&self) -> Result<[u8; NETCODE_KEY_BYTES], String> {
+        let hex = self
+            .private_key
+            .as_ref()
+            .ok_or("no private_key configured, `auth --gen-key` makes one")?;
+        key_from_hex(hex)
+    }
+}
+
+/// A seed from the clock, good enough to tell dungeons apart.
+fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
@@ -0,0 +1,100 @@
+//! What players wear. Items with a `slot` can be equipped, their `bonus`
+//! adds to the wearer's Stats and clients attach their `model`.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::Stats,
+    items::{Inventory, ItemId, ItemStack, Items},
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum EquipSlot {
+    MainHand,
+    OffHand,
+    Armor,
+}
+
+impl EquipSlot {
+    pub const ALL: [EquipSlot; 3] = [EquipSlot::MainHand, EquipSlot::OffHand, EquipSlot::Armor];
+}
+
+/// The equipped items, replicated so everyone sees the weapons.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, Component)]
+pub struct Equipment {
+    pub main_hand: Option<ItemId>,
+    pub off_hand: Option<ItemId>,
+    pub armor: Option<ItemId>,
+}
+
+/// Stats without equipment, the server adds the equipped bonuses on top.
+#[derive(Copy, Clone, Debug, Component)]
+pub struct BaseStats(pub Stats);
+
+impl Equipment {
+    pub fn get(&self, slot: EquipSlot) -> Option<ItemId> {
+        match slot {
+            EquipSlot::MainHand => self.main_hand,
+            EquipSlot::OffHand => self.off_hand,
+            EquipSlot::Armor => self.armor,
+        }
+    }
+
+    fn slot_mut(&mut self, slot: EquipSlot) -> &mut Option<ItemId> {
+        match slot {
+            EquipSlot::MainHand => &mut self.main_hand,
+            EquipSlot::OffHand => &mut self.off_hand,
+            EquipSlot::Armor => &mut self.armor,
+        }
+    }
+
+    /// Equips one of the stack in inventory `slot`, what was equipped
+    /// before goes back into the inventory.
+    pub fn equip(&mut self, inventory: &mut Inventory, slot: usize, items: &Items) -> bool {
+        let has_room = inventory.slots.iter().any(Option::is_none);
+        let Some(Some(stack)) = inventory.slots.get_mut(slot) else {
+            return false;
+        };
+        let Some(equip_slot) = items.get(stack.item).and_then(|item| item.slot) else {
+            return false;
+        };
+        // the old item needs somewhere to go if the stack stays
+        if stack.count > 1 && self.get(equip_slot).is_some() && !has_room {
+            return false;
+        }
+        let item = stack.item;
+        stack.count -= 1;
+        if stack.count == 0 {
+            inventory.slots[slot] = None;
+        }
+        if let Some(old) = self.slot_mut(equip_slot).replace(item) {
+            inventory.add(ItemStack::new(old, 1), items);
+        }
+        true
+    }
+
+    /// Puts the item in `slot` back into the inventory, if it fits.
+    pub fn unequip(&mut self, inventory: &mut Inventory, slot: EquipSlot, items: &Items) -> bool {
+        let Some(item) = self.get(slot) else {
+            return false;
+        };
+        if inventory.add(ItemStack::new(item, 1), items) > 0 {
+            return false;
+        }
+        *self.slot_mut(slot) = None;
+        true
+    }
+
+    /// `base` with the bonus of every equipped item added.
+    pub fn stats(&self, base: &Stats, items: &Items) -> Stats {
+        EquipSlot::ALL
+            .iter()
+            .filter_map(|slot| items.get(self.get(*slot)?))
+            .fold(*base, |stats, item| Stats {
+                attack: stats.attack.saturating_add(item.bonus.attack),
+                defense: stats.defense.saturating_add(item.bonus.defense),
+                max_hp: stats.max_hp.saturating_add(item.bonus.max_hp),
+            })
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{combat::Stats, equipment::EquipSlot};
+
+/// Read at startup when it exists, like the archetypes file.
+pub const ITEMS_FILE: &str = "lib/data/items.ron";
+
+/// Slots in a player's inventory.
+pub const INVENTORY_SLOTS: usize = 16;
+
+/// The position of an item in `data/items.ron`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct ItemId(pub u16);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Item {
+    pub name: String,
+    /// How many fit in one slot.
+    pub max_stack: u16,
+    /// Where it's worn, None for items that can't be equipped.
+    pub slot: Option<EquipSlot>,
+    /// Added to the wearer's stats while equipped.
+    pub bonus: Stats,
+    /// The scene clients attach to the wearer.
+    pub model: Option<String>,
+}
+
+impl Default for Item {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            max_stack: 1,
+            slot: None,
+            bonus: Stats::default(),
+            model: None,
+        }
+    }
+}
+
+/// Every item, client and server load the same file so the ids agree.
+#[derive(Resource, Debug)]
+pub struct Items {
+    items: Vec<Item>,
+}
+
+impl Items {
+    pub fn from_ron(ron: &str) -> Result<Self, String> {
+        let items = ron::from_str(ron).map_err(|err| format!("parsing items: {err}"))?;
+        Ok(Self { items })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let ron = std::fs::read_to_string(path)
+            .map_err(|err| format!("reading {}: {err}", path.display()))?;
+        Self::from_ron(&ron)
+    }
+
+    /// ITEMS_FILE if it's there, the copy built into the binary if not.
+    pub fn load() -> Result<Self, String> {
+        if Path::new(ITEMS_FILE).exists() {
+            return Self::from_file(ITEMS_FILE);
+        }
+        Self::from_ron(include_str!("../data/items.ron"))
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<&Item> {
+        self.items.get(id.0 as usize)
+    }
+
+    pub fn id(&self, name: &str) -> Option<ItemId> {
+        self.iter()
+            .find(|(_, item)| item.name == name)
+            .map(|(id, _)| id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &Item)> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(id, item)| (ItemId(id as u16), item))
+    }
+
+    fn max_stack(&self, id: ItemId) -> u16 {
+        self.get(id).map_or(1, |item| item.max_stack.max(1))
+    }
+}
+
+/// The copy built into the binary, `load` reads ITEMS_FILE.
+impl Default for Items {
+    fn default() -> Self {
+        Self::from_ron(include_str!("../data/items.ron")).unwrap()
+    }
+}
+
+/// `count` of one item, in a slot or lying on the ground.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u16,
+}
+
+impl ItemStack {
+    pub fn new(item: ItemId, count: u16) -> Self {
+        Self { item, count }
+    }
+}
+
+/// What a player carries. The server owns it and only sends it to the
+/// player it belongs to.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Component)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            slots: vec![None; INVENTORY_SLOTS],
+        }
+    }
+}
+
+/// The owner's inventory as of `tick`, a newer one replaces it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InventoryUpdate {
+    pub tick: u64,
+    pub inventory: Inventory,
+}
+
+impl Inventory {
+    /// Tops up stacks of the same item first, then fills empty slots.
+    /// Returns how many didn't fit.
+    pub fn add(&mut self, stack: ItemStack, items: &Items) -> u16 {
+        let max_stack = items.max_stack(stack.item);
+        let mut left = stack.count;
+        for slot in self.slots.iter_mut().flatten() {
+            if left == 0 {
+                break;
+            }
+            if slot.item == stack.item && slot.count < max_stack {
+                let moved = left.min(max_stack - slot.count);
+                slot.count += moved;
+                left -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if left == 0 {
+                break;
+            }
+            if slot.is_none() {
+                let moved = left.min(max_stack);
+                *slot = Some(ItemStack::new(stack.item, moved));
+                left -= moved;
+            }
+        }
+        left
+    }
+
+    pub fn take(&mut self, slot: usize) -> Option<ItemStack> {
+        self.slots.get_mut(slot)?.take()
+    }
+
+    /// Moves the stack in `from` onto `to`. Stacks of the same item are
+    /// merged as far as they fit, different ones swap places.
+    pub fn move_stack(&mut self, from: usize, to: usize, items: &Items) -> bool {
+        if from == to || from >= self.slots.len() || to >= self.slots.len() {
+            return false;
+        }
+        let Some(mut moving) = self.slots[from] else {
+            return false;
+        };
+        match self.slots[to].as_mut() {
+            Some(target) if target.item == moving.item => {
+                let moved = moving
+                    .count
+                    .min(items.max_stack(moving.item).saturating_sub(target.count));
+                if moved == 0 {
+                    return false;
+                }
+                target.count += moved;
+                moving.count -= moved;
+                self.slots[from] = (moving.count > 0).then_some(moving);
+            }
+            _ => self.slots.swap(from, to),
+        }
+        true
+    }
+
+    /// Moves `count` off the stack in `slot` into the first empty slot.
+    pub fn split(&mut self, slot: usize, count: u16) -> bool {
+        let Some(empty) = self.slots.iter().position(|slot| slot.is_none()) else {
+            return false;
+        };
+        let Some(Some(stack)) = self.slots.get_mut(slot) else {
+            return false;
+        };
+        if count == 0 || count >= stack.count {
+            return false;
+        }
+        stack.count -= count;
+        let item = stack.item;
+        self.slots[empty] = Some(ItemStack::new(item, count));
+        true
+    }
+}
@@ -1,10 +1,24 @@
 use bevy::prelude::*;
+use combat::{DamageEvent, DeathEvent};
 use components::{LeftClick, Tile};
+use items::InventoryUpdate;
+use net_id::{NetId, NetIds, NetMapped};
 use serde::{Deserialize, Serialize};
 
+pub mod abilities;
+pub mod archetypes;
+pub mod auth;
 pub mod channels;
+pub mod combat;
 pub mod components;
+pub mod config;
+pub mod equipment;
+pub mod items;
+pub mod nav;
+pub mod net_id;
+pub mod replication;
 pub mod resources;
+pub mod status;
 pub const PROTOCOL_ID: u64 = 7;
 
 #[derive(SystemSet, Debug, Hash, Eq, PartialEq, Clone)]
@@ -35,13 +49,34 @@ impl ClickEvent {
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
-pub struct OpenEvent {
-    pub entity: Entity,
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct OpenEvent<E = Entity> {
+    pub entity: E,
 }
 
+impl NetMapped for OpenEvent {
+    type Net = OpenEvent<NetId>;
+
+    fn to_net(&self, ids: &NetIds) -> Option<Self::Net> {
+        Some(OpenEvent {
+            entity: ids.net_id(self.entity)?,
+        })
+    }
+
+    fn from_net(net: Self::Net, ids: &NetIds) -> Option<Self> {
+        Some(OpenEvent {
+            entity: ids.entity(net.entity)?,
+        })
+    }
+}
+
+/// Events sent on ServerChannel::ServerEvents, with NetIds in place of
+/// entities.
 #[derive(Serialize, Deserialize)]
 pub enum ServerEvents {
-    OpenEvent(OpenEvent),
+    OpenEvent(OpenEvent<NetId>),
+    DamageEvent(DamageEvent<NetId>),
+    DeathEvent(DeathEvent<NetId>),
+    /// Only sent to the player it belongs to.
+    Inventory(InventoryUpdate),
 }
-
@@ -0,0 +1,291 @@
+use bevy::{prelude::*, utils::HashMap};
+use pathfinding::prelude::astar;
+
+use crate::components::{EntityType, OpenState, Tile, Untraversable};
+
+const CHUNK_SIZE: u32 = 16;
+const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// How many entities put floor on a cell and how many block it, so
+/// overlapping walls and pillars can be added and removed independently.
+#[derive(Clone, Copy, Default, Debug)]
+struct NavCell {
+    floor: u16,
+    blockers: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NavContribution {
+    Floor,
+    Blocker,
+}
+
+/// Walkability of every tile, stored in chunks keyed by `Tile.cell` so a
+/// lookup is a hash of the chunk plus an index. Kept up to date by
+/// `update_nav_grid` on both the server and the client.
+#[derive(Resource, Default)]
+pub struct NavGrid {
+    chunks: HashMap<(u32, u32, u32), Box<[NavCell; CHUNK_AREA]>>,
+    contributions: HashMap<Entity, Vec<(Tile, NavContribution)>>,
+}
+
+impl NavGrid {
+    fn key(tile: &Tile) -> ((u32, u32, u32), usize) {
+        let (x, y, z) = tile.cell;
+        let chunk = (x / CHUNK_SIZE, y, z / CHUNK_SIZE);
+        let index = (x % CHUNK_SIZE + (z % CHUNK_SIZE) * CHUNK_SIZE) as usize;
+        (chunk, index)
+    }
+
+    fn cell(&self, tile: &Tile) -> Option<&NavCell> {
+        let (chunk, index) = Self::key(tile);
+        self.chunks.get(&chunk).map(|cells| &cells[index])
+    }
+
+    fn cell_mut(&mut self, tile: &Tile) -> &mut NavCell {
+        let (chunk, index) = Self::key(tile);
+        &mut self
+            .chunks
+            .entry(chunk)
+            .or_insert_with(|| Box::new([NavCell::default(); CHUNK_AREA]))[index]
+    }
+
+    pub fn is_walkable(&self, tile: &Tile) -> bool {
+        self.cell(tile)
+            .is_some_and(|cell| cell.floor > 0 && cell.blockers == 0)
+    }
+
+    /// A wall, a closed door or something Untraversable is on the tile.
+    pub fn is_blocked(&self, tile: &Tile) -> bool {
+        self.cell(tile).is_some_and(|cell| cell.blockers > 0)
+    }
+
+    /// Nothing blocks the Bresenham line between `from` and `to`, the end
+    /// tiles themselves aren't checked.
+    pub fn line_of_sight(&self, from: &Tile, to: &Tile) -> bool {
+        from.cell.1 == to.cell.1 && line(from, to).iter().all(|tile| !self.is_blocked(tile))
+    }
+
+    /// Replaces what `entity` contributes to the grid.
+    pub fn set(&mut self, entity: Entity, contributions: Vec<(Tile, NavContribution)>) {
+        if self.contributions.get(&entity) == Some(&contributions) {
+            return;
+        }
+        self.remove(entity);
+        if contributions.is_empty() {
+            return;
+        }
+        for (tile, contribution) in contributions.iter() {
+            let cell = self.cell_mut(tile);
+            match contribution {
+                NavContribution::Floor => cell.floor += 1,
+                NavContribution::Blocker => cell.blockers += 1,
+            }
+        }
+        self.contributions.insert(entity, contributions);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(contributions) = self.contributions.remove(&entity) else {
+            return;
+        };
+        for (tile, contribution) in contributions.iter() {
+            let cell = self.cell_mut(tile);
+            match contribution {
+                NavContribution::Floor => cell.floor -= 1,
+                NavContribution::Blocker => cell.blockers -= 1,
+            }
+        }
+    }
+
+    /// Walkable neighbours with the 10/14 step costs. Diagonal steps need
+    /// both tiles they cut past to be walkable, so nobody squeezes
+    /// between a wall and a pillar.
+    pub fn successors(&self, current: &Tile) -> Vec<(Tile, u32)> {
+        let mut neighbours = vec![];
+        for dx in -1i64..=1 {
+            for dz in -1i64..=1 {
+                if dx == 0 && dz == 0 {
+                    continue;
+                }
+                let Some(tile) = offset(current, dx, dz) else {
+                    continue;
+                };
+                if !self.is_walkable(&tile) {
+                    continue;
+                }
+                if dx != 0 && dz != 0 {
+                    let side_x = offset(current, dx, 0);
+                    let side_z = offset(current, 0, dz);
+                    let clear = |side: Option<Tile>| side.is_some_and(|t| self.is_walkable(&t));
+                    if !clear(side_x) || !clear(side_z) {
+                        continue;
+                    }
+                    neighbours.push((tile, 14));
+                } else {
+                    neighbours.push((tile, 10));
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Steps from `start` to `goal`, not including `start`. With `adjacent`
+    /// the path ends next to `goal` instead, for clicks on doors and mobs.
+    pub fn find_path(&self, start: Tile, goal: Tile, adjacent: bool) -> Option<Vec<Tile>> {
+        if !adjacent && !self.is_walkable(&goal) {
+            return None;
+        }
+        self.find_path_where(start, goal, |node| {
+            *node == goal || (adjacent && is_adjacent(node, &goal))
+        })
+    }
+
+    /// Steps from `start` to the first tile `done` accepts, searching
+    /// towards `goal`. Empty if `start` is already done.
+    pub fn find_path_where(
+        &self,
+        start: Tile,
+        goal: Tile,
+        done: impl Fn(&Tile) -> bool,
+    ) -> Option<Vec<Tile>> {
+        let (mut path, _cost) = astar(
+            &start,
+            |current| self.successors(current),
+            |pos| distance(pos, &goal),
+            done,
+        )?;
+        path.remove(0);
+        Some(path)
+    }
+}
+
+/// The tiles strictly between `from` and `to` on a Bresenham line, on
+/// the floor of `from`.
+pub fn line(from: &Tile, to: &Tile) -> Vec<Tile> {
+    let (mut x, y, mut z) = (from.cell.0 as i64, from.cell.1, from.cell.2 as i64);
+    let (end_x, end_z) = (to.cell.0 as i64, to.cell.2 as i64);
+    let dx = (end_x - x).abs();
+    let dz = -(end_z - z).abs();
+    let step_x = if x < end_x { 1 } else { -1 };
+    let step_z = if z < end_z { 1 } else { -1 };
+    let mut error = dx + dz;
+    let mut tiles = vec![];
+    loop {
+        let doubled = 2 * error;
+        if doubled >= dz {
+            if x == end_x {
+                break;
+            }
+            error += dz;
+            x += step_x;
+        }
+        if doubled <= dx {
+            if z == end_z {
+                break;
+            }
+            error += dx;
+            z += step_z;
+        }
+        if x == end_x && z == end_z {
+            break;
+        }
+        tiles.push(Tile::new((x as u32, y, z as u32)));
+    }
+    tiles
+}
+
+fn offset(tile: &Tile, dx: i64, dz: i64) -> Option<Tile> {
+    let x = u32::try_from(tile.cell.0 as i64 + dx).ok()?;
+    let z = u32::try_from(tile.cell.2 as i64 + dz).ok()?;
+    Some(Tile::new((x, tile.cell.1, z)))
+}
+
+/// Octile distance using the same 10/14 costs as the path steps.
+pub fn distance(a: &Tile, b: &Tile) -> u32 {
+    let dx = a.cell.0.abs_diff(b.cell.0);
+    let dz = a.cell.2.abs_diff(b.cell.2);
+    10 * dx.max(dz) + 4 * dx.min(dz)
+}
+
+pub fn is_adjacent(a: &Tile, b: &Tile) -> bool {
+    a != b
+        && a.cell.1 == b.cell.1
+        && a.cell.0.abs_diff(b.cell.0) <= 1
+        && a.cell.2.abs_diff(b.cell.2) <= 1
+}
+
+/// What an entity adds to the grid: floor tiles are floor, walls and
+/// anything `Untraversable` block their tile, arches their pillars and
+/// doors their passage while they aren't open.
+pub fn contributions(
+    tile: &Tile,
+    entity_type: &EntityType,
+    untraversable: Option<&Untraversable>,
+    open_state: Option<&OpenState>,
+) -> Vec<(Tile, NavContribution)> {
+    let mut contributions = vec![];
+    if untraversable.is_some() {
+        contributions.push((*tile, NavContribution::Blocker));
+    }
+    match entity_type {
+        EntityType::Tile => contributions.push((*tile, NavContribution::Floor)),
+        EntityType::Wall(_) => contributions.push((*tile, NavContribution::Blocker)),
+        EntityType::Arch(arch) => {
+            for pillar in arch.pillars(*tile) {
+                contributions.push((pillar, NavContribution::Blocker));
+            }
+        }
+        EntityType::Door(door) if open_state != Some(&OpenState::Open) => {
+            contributions.push((door.passage(*tile), NavContribution::Blocker));
+        }
+        _ => (),
+    }
+    contributions
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_nav_grid(
+    mut grid: ResMut<NavGrid>,
+    changed: Query<
+        (
+            Entity,
+            &Tile,
+            &EntityType,
+            Option<&Untraversable>,
+            Option<&OpenState>,
+        ),
+        Or<(
+            Changed<Tile>,
+            Changed<OpenState>,
+            Added<Untraversable>,
+            Added<EntityType>,
+        )>,
+    >,
+    entities: Query<(
+        &Tile,
+        &EntityType,
+        Option<&Untraversable>,
+        Option<&OpenState>,
+    )>,
+    mut despawned: RemovedComponents<EntityType>,
+    mut made_traversable: RemovedComponents<Untraversable>,
+) {
+    for (entity, tile, entity_type, untraversable, open_state) in changed.iter() {
+        grid.set(
+            entity,
+            contributions(tile, entity_type, untraversable, open_state),
+        );
+    }
+    for entity in despawned.iter() {
+        grid.remove(entity);
+    }
+    for entity in made_traversable.iter() {
+        if let Ok((tile, entity_type, untraversable, open_state)) = entities.get(entity) {
+            grid.set(
+                entity,
+                contributions(tile, entity_type, untraversable, open_state),
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::components::{LeftClick, PlayerCommand, Target};
+
+/// How an entity is referenced on the wire. The server hands them out and
+/// never reuses one, unlike Entity whose index comes back with a new
+/// generation after a despawn.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct NetId(pub u64);
+
+/// Both directions between NetIds and the entities of this world.
+#[derive(Resource, Default)]
+pub struct NetIds {
+    entities: HashMap<NetId, Entity>,
+    ids: HashMap<Entity, NetId>,
+    next: u64,
+}
+
+impl NetIds {
+    /// Gives `entity` a new NetId, server only.
+    pub fn allocate(&mut self, entity: Entity) -> NetId {
+        self.next += 1;
+        let id = NetId(self.next);
+        self.insert(id, entity);
+        id
+    }
+
+    pub fn insert(&mut self, id: NetId, entity: Entity) {
+        self.entities.insert(id, entity);
+        self.ids.insert(entity, id);
+    }
+
+    pub fn entity(&self, id: NetId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+
+    pub fn net_id(&self, entity: Entity) -> Option<NetId> {
+        self.ids.get(&entity).copied()
+    }
+
+    pub fn remove(&mut self, id: NetId) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+        self.ids.remove(&entity);
+        Some(entity)
+    }
+
+    pub fn remove_entity(&mut self, entity: Entity) -> Option<NetId> {
+        let id = self.ids.remove(&entity)?;
+        self.entities.remove(&id);
+        Some(id)
+    }
+
+    /// Forgets every entity `keep` returns false for.
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        let ids = &mut self.ids;
+        self.entities.retain(|_, entity| {
+            let kept = keep(*entity);
+            if !kept {
+                ids.remove(entity);
+            }
+            kept
+        });
+    }
+}
+
+/// Types that reference entities and go over the network. They are sent as
+/// `Net`, with every Entity swapped for its NetId and back on arrival.
+/// None if a referenced entity has no counterpart on the other side.
+pub trait NetMapped: Sized {
+    type Net: Serialize + DeserializeOwned;
+
+    fn to_net(&self, ids: &NetIds) -> Option<Self::Net>;
+
+    fn from_net(net: Self::Net, ids: &NetIds) -> Option<Self>;
+}
+
+impl NetMapped for Target {
+    type Net = Option<NetId>;
+
+    fn to_net(&self, ids: &NetIds) -> Option<Self::Net> {
+        Some(self.0.and_then(|entity| ids.net_id(entity)))
+    }
+
+    fn from_net(net: Self::Net, ids: &NetIds) -> Option<Self> {
+        Some(Target(net.and_then(|id| ids.entity(id))))
+    }
+}
+
+impl NetMapped for LeftClick {
+    type Net = LeftClick<NetId>;
+
+    fn to_net(&self, ids: &NetIds) -> Option<Self::Net> {
+        self.map(|entity| ids.net_id(entity))
+    }
+
+    fn from_net(net: Self::Net, ids: &NetIds) -> Option<Self> {
+        net.map(|id| ids.entity(id))
+    }
+}
+
+impl NetMapped for PlayerCommand {
+    type Net = PlayerCommand<NetId>;
+
+    fn to_net(&self, ids: &NetIds) -> Option<Self::Net> {
+        Some(match self {
+            PlayerCommand::LeftClick(left_click, tile) => {
+                PlayerCommand::LeftClick(left_click.to_net(ids)?, *tile)
+            }
+            PlayerCommand::UseAbility(ability, target) => {
+                let target = match target {
+                    Some(target) => Some(ids.net_id(*target)?),
+                    None => None,
+                };
+                PlayerCommand::UseAbility(*ability, target)
+            }
+            PlayerCommand::DropItem(slot) => PlayerCommand::DropItem(*slot),
+            PlayerCommand::MoveItem(from, to) => PlayerCommand::MoveItem(*from, *to),
+            PlayerCommand::SplitStack(slot, count) => PlayerCommand::SplitStack(*slot, *count),
+            PlayerCommand::Equip(slot) => PlayerCommand::Equip(*slot),
+            PlayerCommand::Unequip(slot) => PlayerCommand::Unequip(*slot),
+        })
+    }
+
+    fn from_net(net: Self::Net, ids: &NetIds) -> Option<Self> {
+        Some(match net {
+            PlayerCommand::LeftClick(left_click, tile) => {
+                PlayerCommand::LeftClick(LeftClick::from_net(left_click, ids)?, tile)
+            }
+            PlayerCommand::UseAbility(ability, target) => {
+                let target = match target {
+                    Some(target) => Some(ids.entity(target)?),
+                    None => None,
+                };
+                PlayerCommand::UseAbility(ability, target)
+            }
+            PlayerCommand::DropItem(slot) => PlayerCommand::DropItem(slot),
+            PlayerCommand::MoveItem(from, to) => PlayerCommand::MoveItem(from, to),
+            PlayerCommand::SplitStack(slot, count) => PlayerCommand::SplitStack(slot, count),
+            PlayerCommand::Equip(slot) => PlayerCommand::Equip(slot),
+            PlayerCommand::Unequip(slot) => PlayerCommand::Unequip(slot),
+        })
+    }
+}
@@ -0,0 +1,303 @@
+use std::any::{type_name, TypeId};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    combat::{Mana, Stats},
+    components::{CombatState, ComponentType, Health, OpenState, Target, Tile},
+    equipment::Equipment,
+    net_id::{NetId, NetIds, NetMapped},
+    status::StatusEffects,
+};
+
+/// Every component that is synced from the server to the clients.
+/// The position in this list is the id used on the wire, both binaries
+/// build it through `ReplicationPlugin` so the ids can't drift apart.
+/// Adding a synced component is one line here.
+fn replicated_components(app: &mut App) {
+    app.replicate::<Health>()
+        .replicate::<Tile>()
+        .replicate_mapped::<Target>()
+        .replicate::<CombatState>()
+        .replicate::<OpenState>()
+        .replicate::<Stats>()
+        .replicate::<Mana>()
+        .replicate::<StatusEffects>()
+        .replicate::<Equipment>();
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReplicationSide {
+    Server,
+    Client,
+}
+
+/// Runs the server side change detection, send_snapshots has to run after it.
+#[derive(SystemSet, Debug, Hash, Eq, PartialEq, Clone)]
+pub struct ReplicationSet;
+
+type ApplyFn = fn(&mut World, Entity, &[u8], &NetIds);
+
+struct Registration {
+    name: &'static str,
+    apply: ApplyFn,
+}
+
+#[derive(Resource)]
+pub struct ReplicationRegistry {
+    side: ReplicationSide,
+    ids: HashMap<TypeId, u16>,
+    registrations: Vec<Registration>,
+}
+
+impl ReplicationRegistry {
+    pub fn new(side: ReplicationSide) -> Self {
+        Self {
+            side,
+            ids: HashMap::default(),
+            registrations: vec![],
+        }
+    }
+
+    pub fn id<C: Component>(&self) -> Option<u16> {
+        self.ids.get(&TypeId::of::<C>()).copied()
+    }
+
+    pub fn name(&self, id: u16) -> Option<&'static str> {
+        self.registrations
+            .get(id as usize)
+            .map(|registration| registration.name)
+    }
+
+    fn register<C: Component>(&mut self, apply: ApplyFn) -> u16 {
+        let name = type_name::<C>();
+        assert!(
+            !self.ids.contains_key(&TypeId::of::<C>()),
+            "{name} is already replicated"
+        );
+        let id = self.registrations.len() as u16;
+        self.ids.insert(TypeId::of::<C>(), id);
+        self.registrations.push(Registration { name, apply });
+        id
+    }
+
+    /// Inserts the component carried by `component` on `entity`.
+    /// `ids` resolves the NetIds it references.
+    pub fn apply(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        component: &ComponentType,
+        ids: &NetIds,
+    ) {
+        match self.registrations.get(component.id as usize) {
+            Some(registration) => (registration.apply)(world, entity, &component.data, ids),
+            None => warn!("received unknown component id {}", component.id),
+        }
+    }
+}
+
+pub trait AppReplicateExt {
+    fn replicate<C>(&mut self) -> &mut Self
+    where
+        C: Component + Clone + PartialEq + Serialize + DeserializeOwned;
+
+    /// For components that reference entities, they go out as `C::Net`.
+    fn replicate_mapped<C>(&mut self) -> &mut Self
+    where
+        C: Component + Clone + PartialEq + NetMapped;
+}
+
+impl AppReplicateExt for App {
+    fn replicate<C>(&mut self) -> &mut Self
+    where
+        C: Component + Clone + PartialEq + Serialize + DeserializeOwned,
+    {
+        if register::<C>(self, apply_component::<C>) {
+            self.add_system(
+                replicate_changes::<C>
+                    .in_set(ReplicationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+        }
+        self
+    }
+
+    fn replicate_mapped<C>(&mut self) -> &mut Self
+    where
+        C: Component + Clone + PartialEq + NetMapped,
+    {
+        if register::<C>(self, apply_mapped_component::<C>) {
+            self.add_system(
+                replicate_mapped_changes::<C>
+                    .in_set(ReplicationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+        }
+        self
+    }
+}
+
+/// Registers `C`, true if this is the server and its changes need sending.
+fn register<C: Component>(app: &mut App, apply: ApplyFn) -> bool {
+    let mut registry = app.world.resource_mut::<ReplicationRegistry>();
+    registry.register::<C>(apply);
+    registry.side == ReplicationSide::Server
+}
+
+/// The encoded replicated components of one entity, keyed by component id.
+pub type EntityState = HashMap<u16, Vec<u8>>;
+
+/// The encoded replicated components of a set of entities.
+pub type SnapshotState = HashMap<NetId, EntityState>;
+
+/// Every replicated component in the server world, kept up to date by
+/// `replicate_changes`. Snapshots are cut out of this per client.
+#[derive(Resource, Default)]
+pub struct ReplicationState {
+    pub entities: HashMap<Entity, EntityState>,
+}
+
+/// All replicated components that changed for a client's scoped entities
+/// since the snapshot at `baseline`, 0 being a snapshot of nothing.
+/// `removed` are entities of the baseline that aren't in this one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub baseline: u64,
+    pub changes: Vec<(NetId, ComponentType)>,
+    pub removed: Vec<NetId>,
+}
+
+impl Snapshot {
+    /// Applies this snapshot on top of the state of its baseline.
+    pub fn decode(&self, baseline: &SnapshotState) -> SnapshotState {
+        let mut state = baseline.clone();
+        for id in self.removed.iter() {
+            state.remove(id);
+        }
+        for (id, component) in self.changes.iter() {
+            state
+                .entry(*id)
+                .or_default()
+                .insert(component.id, component.data.clone());
+        }
+        state
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotAck {
+    pub tick: u64,
+}
+
+/// Keeps the encoded state of every changed `C` in the ReplicationState.
+pub fn replicate_changes<C: Component + Serialize>(
+    components: Query<(Entity, &C), Changed<C>>,
+    removed: RemovedComponents<C>,
+    registry: Res<ReplicationRegistry>,
+    mut state: ResMut<ReplicationState>,
+) {
+    let Some(id) = registry.id::<C>() else {
+        return;
+    };
+    for (entity, component) in components.iter() {
+        let data = bincode::serialize(component).unwrap();
+        state.entities.entry(entity).or_default().insert(id, data);
+    }
+    state.remove(id, removed);
+}
+
+/// replicate_changes for components that go out as `C::Net`.
+pub fn replicate_mapped_changes<C: Component + NetMapped>(
+    components: Query<(Entity, &C), Changed<C>>,
+    removed: RemovedComponents<C>,
+    registry: Res<ReplicationRegistry>,
+    ids: Res<NetIds>,
+    mut state: ResMut<ReplicationState>,
+) {
+    let Some(id) = registry.id::<C>() else {
+        return;
+    };
+    for (entity, component) in components.iter() {
+        if let Some(net) = component.to_net(&ids) {
+            let data = bincode::serialize(&net).unwrap();
+            state.entities.entry(entity).or_default().insert(id, data);
+        }
+    }
+    state.remove(id, removed);
+}
+
+impl ReplicationState {
+    fn remove<C: Component>(&mut self, id: u16, mut removed: RemovedComponents<C>) {
+        for entity in removed.iter() {
+            if let Some(components) = self.entities.get_mut(&entity) {
+                components.remove(&id);
+                if components.is_empty() {
+                    self.entities.remove(&entity);
+                }
+            }
+        }
+    }
+}
+
+fn apply_component<C>(world: &mut World, entity: Entity, data: &[u8], _ids: &NetIds)
+where
+    C: Component + Clone + PartialEq + DeserializeOwned,
+{
+    if let Ok(component) = bincode::deserialize::<C>(data) {
+        insert_if_changed(world, entity, component);
+    }
+}
+
+fn apply_mapped_component<C>(world: &mut World, entity: Entity, data: &[u8], ids: &NetIds)
+where
+    C: Component + Clone + PartialEq + NetMapped,
+{
+    let Ok(net) = bincode::deserialize::<C::Net>(data) else {
+        return;
+    };
+    if let Some(component) = C::from_net(net, ids) {
+        insert_if_changed(world, entity, component);
+    }
+}
+
+/// Only touches the component when the value differs, so `Changed<C>` on
+/// the client means the server actually changed it.
+fn insert_if_changed<C: Component + PartialEq>(world: &mut World, entity: Entity, component: C) {
+    if let Some(mut entity_mut) = world.get_entity_mut(entity) {
+        if entity_mut.get::<C>() != Some(&component) {
+            entity_mut.insert(component);
+        }
+    }
+}
+
+pub struct ReplicationPlugin {
+    pub side: ReplicationSide,
+}
+
+impl ReplicationPlugin {
+    pub fn server() -> Self {
+        Self {
+            side: ReplicationSide::Server,
+        }
+    }
+
+    pub fn client() -> Self {
+        Self {
+            side: ReplicationSide::Client,
+        }
+    }
+}
+
+impl Plugin for ReplicationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplicationRegistry::new(self.side));
+        app.init_resource::<NetIds>();
+        if self.side == ReplicationSide::Server {
+            app.init_resource::<ReplicationState>();
+        }
+        replicated_components(app);
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::resources::Tick;
+
+/// Ticks between the damage of Poison and the healing of Regen.
+pub const STATUS_PERIOD_TICKS: u64 = 5;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// A point of damage per stack every period.
+    Poison,
+    /// A point of health per stack every period.
+    Regen,
+    /// Every stack adds a tick between steps and a quarter to cooldowns.
+    Slow,
+    /// Can't move or use abilities.
+    Stun,
+    /// Every stack takes a quarter off the damage dealt.
+    Weaken,
+}
+
+impl StatusKind {
+    pub fn max_stacks(&self) -> u16 {
+        match self {
+            StatusKind::Poison => 5,
+            StatusKind::Regen | StatusKind::Slow | StatusKind::Weaken => 3,
+            StatusKind::Stun => 1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub stacks: u16,
+    /// The tick it wears off.
+    pub until: u64,
+}
+
+/// Active buffs and debuffs, replicated so clients can show them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, Component)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    /// Adds a stack of `kind` and restarts its duration.
+    pub fn apply(&mut self, kind: StatusKind, ticks: u64, tick: &Tick) {
+        let until = tick.tick.saturating_add(ticks);
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => {
+                effect.stacks = effect.stacks.saturating_add(1).min(kind.max_stacks());
+                effect.until = effect.until.max(until);
+            }
+            None => self.effects.push(StatusEffect {
+                kind,
+                stacks: 1,
+                until,
+            }),
+        }
+    }
+
+    pub fn has_expired(&self, tick: &Tick) -> bool {
+        self.effects.iter().any(|effect| effect.until <= tick.tick)
+    }
+
+    pub fn expire(&mut self, tick: &Tick) {
+        self.effects.retain(|effect| effect.until > tick.tick);
+    }
+
+    pub fn stacks(&self, kind: StatusKind) -> u16 {
+        self.effects
+            .iter()
+            .find(|effect| effect.kind == kind)
+            .map_or(0, |effect| effect.stacks)
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.stacks(StatusKind::Stun) > 0
+    }
+
+    /// Ticks between two steps, None while stunned.
+    pub fn move_interval(&self) -> Option<u64> {
+        if self.is_stunned() {
+            return None;
+        }
+        Some(1 + self.stacks(StatusKind::Slow) as u64)
+    }
+
+    pub fn cooldown(&self, ticks: u64) -> u64 {
+        let slow = self.stacks(StatusKind::Slow) as u64;
+        ticks.saturating_add(ticks.saturating_mul(slow) / 4)
+    }
+
+    /// Damage dealt, never less than 1.
+    pub fn damage(&self, damage: u16) -> u16 {
+        let weaken = self.stacks(StatusKind::Weaken).min(3);
+        // a quarter per stack in u32, damage * 3 doesn't fit a u16
+        let weakened = u32::from(damage) * u32::from(weaken) / 4;
+        (damage - weakened as u16).max(1)
+    }
+}
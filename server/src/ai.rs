@@ -0,0 +1,443 @@
+//! Mob behaviour as a seldom_state machine. Mobs idle and wander around
+//! their MobRange, aggro on the top of their ThreatTable, chase it around
+//! walls, attack through the same CombatEvent players use and leash back
+//! home when nobody in the range is left to fight.
+
+use bevy::prelude::*;
+use lib::{
+    abilities::{Abilities, AbilityId, CoolDowns},
+    combat::Stats,
+    components::{EntityType, Health, Target, Tile},
+    nav::NavGrid,
+    resources::Tick,
+    status::StatusEffects,
+};
+use rand::{seq::IteratorRandom, Rng};
+use seldom_state::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{combat::CombatEvent, threat::ThreatTable};
+
+/// Ticks a mob stands around before wandering off again.
+const IDLE_TICKS: std::ops::Range<u64> = 20..60;
+/// Ticks between noticing a player and going after it.
+const AGGRO_TICKS: u64 = 5;
+
+/// The area a mob wanders in and chases players through.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct MobRange {
+    pub top_left: Tile,
+    pub bottom_right: Tile,
+}
+
+impl MobRange {
+    pub fn check(&self, pos: &Tile) -> bool {
+        let x = pos.cell.0;
+        let z = pos.cell.2;
+
+        let tl_x = self.top_left.cell.0;
+        let tl_z = self.top_left.cell.2;
+
+        let br_x = self.bottom_right.cell.0;
+        let br_z = self.bottom_right.cell.2;
+
+        // areas are on one floor
+        pos.cell.1 == self.top_left.cell.1 && x >= tl_x && x <= br_x && z >= tl_z && z <= br_z
+    }
+}
+
+/// How a mob behaves, the state machine decides what it is doing.
+#[derive(Component, Clone, Debug)]
+pub struct MobAi {
+    /// Where it returns to after leashing.
+    pub home: Tile,
+    /// Players this many tiles away in line of sight get aggroed.
+    pub aggro_radius: u32,
+    /// Ticks between steps.
+    pub step_ticks: u64,
+    /// Used on the target while in reach.
+    pub ability: AbilityId,
+    next_step: u64,
+    wait_until: u64,
+    wander_to: Option<Tile>,
+}
+
+impl MobAi {
+    pub fn new(home: Tile, aggro_radius: u32, step_ticks: u64) -> Self {
+        Self {
+            home,
+            aggro_radius,
+            step_ticks,
+            ability: AbilityId::AUTO_ATTACK,
+            next_step: 0,
+            wait_until: 0,
+            wander_to: None,
+        }
+    }
+
+    /// Whether the mob may step this tick, slowed mobs step less often
+    /// and stunned ones not at all.
+    fn step_ready(&mut self, statuses: Option<&StatusEffects>, tick: &Tick) -> bool {
+        if tick.tick < self.next_step {
+            return false;
+        }
+        let Some(interval) = statuses.map_or(Some(1), |statuses| statuses.move_interval()) else {
+            return false;
+        };
+        self.next_step = tick.tick + self.step_ticks * interval;
+        true
+    }
+}
+
+#[derive(Clone, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Idle;
+
+#[derive(Clone, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Wander;
+
+#[derive(Clone, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Aggro;
+
+#[derive(Clone, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Chase;
+
+#[derive(Clone, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Attack;
+
+#[derive(Clone, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Leash;
+
+#[derive(Clone, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Return;
+
+/// The mob has something to go after.
+#[derive(Clone, Copy, FromReflect, Reflect)]
+pub struct HasTarget;
+
+impl BoolTrigger for HasTarget {
+    type Param<'w, 's> = Query<'w, 's, &'static Target>;
+
+    fn trigger(&self, entity: Entity, targets: &Self::Param<'_, '_>) -> bool {
+        targets.get(entity).is_ok_and(|target| target.0.is_some())
+    }
+}
+
+/// The target is in reach of the mob's ability.
+#[derive(Clone, Copy, FromReflect, Reflect)]
+pub struct InAttackRange;
+
+impl BoolTrigger for InAttackRange {
+    type Param<'w, 's> = (
+        Query<'w, 's, (&'static Tile, &'static Target, &'static MobAi)>,
+        Query<'w, 's, &'static Tile>,
+        Res<'w, NavGrid>,
+        Res<'w, Abilities>,
+    );
+
+    fn trigger(&self, entity: Entity, param: &Self::Param<'_, '_>) -> bool {
+        let (mobs, tiles, grid, abilities) = param;
+        let Ok((tile, target, ai)) = mobs.get(entity) else {
+            return false;
+        };
+        let (Some(target_tile), Some(ability)) = (
+            target.0.and_then(|target| tiles.get(target).ok()),
+            abilities.get(ai.ability),
+        ) else {
+            return false;
+        };
+        ability.range.reaches(tile, target_tile, grid)
+    }
+}
+
+/// The target is gone or left the MobRange.
+#[derive(Clone, Copy, FromReflect, Reflect)]
+pub struct ShouldLeash;
+
+impl BoolTrigger for ShouldLeash {
+    type Param<'w, 's> = (
+        Query<'w, 's, (&'static Target, &'static MobRange)>,
+        Query<'w, 's, &'static Tile>,
+    );
+
+    fn trigger(&self, entity: Entity, param: &Self::Param<'_, '_>) -> bool {
+        let (mobs, tiles) = param;
+        let Ok((target, range)) = mobs.get(entity) else {
+            return true;
+        };
+        !target
+            .0
+            .and_then(|target| tiles.get(target).ok())
+            .is_some_and(|tile| range.check(tile))
+    }
+}
+
+/// Registers the triggers mob_state_machine uses.
+pub struct MobAiPlugin;
+
+impl Plugin for MobAiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(TriggerPlugin::<HasTarget>::default());
+        app.add_plugin(TriggerPlugin::<InAttackRange>::default());
+        app.add_plugin(TriggerPlugin::<ShouldLeash>::default());
+    }
+}
+
+/// The state machine every mob with a MobAi runs.
+pub fn mob_state_machine() -> StateMachine {
+    StateMachine::new(Idle)
+        .trans::<Idle>(HasTarget, Aggro)
+        .trans::<Idle>(DoneTrigger::Success, Wander)
+        .trans::<Wander>(HasTarget, Aggro)
+        .trans::<Wander>(DoneTrigger::Success, Idle)
+        .trans::<Aggro>(ShouldLeash, Leash)
+        .trans::<Aggro>(DoneTrigger::Success, Chase)
+        .trans::<Chase>(ShouldLeash, Leash)
+        .trans::<Chase>(InAttackRange, Attack)
+        .trans::<Chase>(DoneTrigger::Failure, Leash)
+        .trans::<Attack>(ShouldLeash, Leash)
+        .trans::<Attack>(NotTrigger(InAttackRange), Chase)
+        .insert_on_enter::<Leash>((Target(None), ThreatTable::default()))
+        .trans::<Leash>(AlwaysTrigger, Return)
+        .trans::<Return>(DoneTrigger::Success, Idle)
+}
+
+/// Mobs that aren't leashing notice the players in aggro radius they can
+/// see, the closer the more threat they get.
+#[allow(clippy::type_complexity)]
+pub fn acquire_targets(
+    mut mobs: Query<
+        (&Tile, &MobAi, &MobRange, &mut ThreatTable),
+        (Without<Leash>, Without<Return>),
+    >,
+    players: Query<(Entity, &Tile, &EntityType, &Health)>,
+    grid: Res<NavGrid>,
+) {
+    for (tile, ai, range, mut table) in mobs.iter_mut() {
+        for (player, player_tile, entity_type, health) in players.iter() {
+            let distance = chebyshev(tile, player_tile);
+            if matches!(entity_type, EntityType::Player(_))
+                && health.hp > 0
+                && range.check(player_tile)
+                && distance <= ai.aggro_radius
+                && grid.line_of_sight(tile, player_tile)
+            {
+                table.keep(player, ai.aggro_radius + 1 - distance);
+            }
+        }
+    }
+}
+
+fn chebyshev(from: &Tile, to: &Tile) -> u32 {
+    from.cell
+        .0
+        .abs_diff(to.cell.0)
+        .max(from.cell.2.abs_diff(to.cell.2))
+}
+
+/// Stands around for a while before wandering.
+pub fn idle(
+    mut commands: Commands,
+    mut mobs: Query<(Entity, &mut MobAi), With<Idle>>,
+    entered: Query<(), Added<Idle>>,
+    tick: Res<Tick>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, mut ai) in mobs.iter_mut() {
+        if entered.contains(entity) {
+            ai.wait_until = tick.tick + rng.gen_range(IDLE_TICKS);
+        } else if tick.tick >= ai.wait_until {
+            commands.entity(entity).insert(Done::Success);
+        }
+    }
+}
+
+/// Walks to a random tile of the MobRange, around walls.
+#[allow(clippy::type_complexity)]
+pub fn wander(
+    mut commands: Commands,
+    mut mobs: Query<
+        (
+            Entity,
+            &mut MobAi,
+            &mut Tile,
+            &MobRange,
+            Option<&StatusEffects>,
+        ),
+        With<Wander>,
+    >,
+    entered: Query<(), Added<Wander>>,
+    grid: Res<NavGrid>,
+    tick: Res<Tick>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, mut ai, mut tile, range, statuses) in mobs.iter_mut() {
+        if entered.contains(entity) {
+            let (top_left, bottom_right) = (range.top_left.cell, range.bottom_right.cell);
+            let here = *tile;
+            ai.wander_to = (top_left.0..=bottom_right.0)
+                .flat_map(|x| {
+                    (top_left.2..=bottom_right.2).map(move |z| Tile::new((x, here.cell.1, z)))
+                })
+                .filter(|destination| *destination != here && grid.is_walkable(destination))
+                .choose(&mut rng);
+        }
+        if !ai.step_ready(statuses, &tick) {
+            continue;
+        }
+        let next = ai
+            .wander_to
+            .and_then(|destination| grid.find_path(*tile, destination, false))
+            .and_then(|path| path.first().copied());
+        match next {
+            Some(next) if range.check(&next) => *tile = next,
+            _ => {
+                ai.wander_to = None;
+                commands.entity(entity).insert(Done::Success);
+            }
+        }
+    }
+}
+
+/// Takes a moment to notice the target before chasing it.
+pub fn aggro(
+    mut commands: Commands,
+    mut mobs: Query<(Entity, &mut MobAi), With<Aggro>>,
+    entered: Query<(), Added<Aggro>>,
+    tick: Res<Tick>,
+) {
+    for (entity, mut ai) in mobs.iter_mut() {
+        if entered.contains(entity) {
+            ai.wait_until = tick.tick + AGGRO_TICKS;
+        } else if tick.tick >= ai.wait_until {
+            commands.entity(entity).insert(Done::Success);
+        }
+    }
+}
+
+/// Walks into reach of the target, giving up when there is no way there
+/// inside the MobRange.
+#[allow(clippy::type_complexity)]
+pub fn chase(
+    mut commands: Commands,
+    mut mobs: Query<
+        (
+            Entity,
+            &mut MobAi,
+            &mut Tile,
+            &Target,
+            &MobRange,
+            Option<&StatusEffects>,
+        ),
+        With<Chase>,
+    >,
+    targets: Query<&Tile, Without<Chase>>,
+    abilities: Res<Abilities>,
+    grid: Res<NavGrid>,
+    tick: Res<Tick>,
+) {
+    for (entity, mut ai, mut tile, target, range, statuses) in mobs.iter_mut() {
+        let (Some(target_tile), Some(ability)) = (
+            target.0.and_then(|target| targets.get(target).ok()),
+            abilities.get(ai.ability),
+        ) else {
+            continue;
+        };
+        if !ai.step_ready(statuses, &tick) {
+            continue;
+        }
+        let Some(path) = ability.range.path_into_range(&grid, *tile, *target_tile) else {
+            commands.entity(entity).insert(Done::Failure);
+            continue;
+        };
+        match path.first() {
+            Some(next) if range.check(next) => *tile = *next,
+            Some(_) => {
+                commands.entity(entity).insert(Done::Failure);
+            }
+            // already in reach, InAttackRange takes over
+            None => {}
+        }
+    }
+}
+
+/// Uses the mob's ability on the target whenever it's off cooldown.
+#[allow(clippy::type_complexity)]
+pub fn attack(
+    mut mobs: Query<
+        (
+            Entity,
+            &MobAi,
+            &Target,
+            &mut CoolDowns,
+            Option<&StatusEffects>,
+        ),
+        With<Attack>,
+    >,
+    abilities: Res<Abilities>,
+    tick: Res<Tick>,
+    mut combat_event: EventWriter<CombatEvent>,
+) {
+    for (entity, ai, target, mut cooldowns, statuses) in mobs.iter_mut() {
+        let (Some(target), Some(ability)) = (target.0, abilities.get(ai.ability)) else {
+            continue;
+        };
+        let stunned = statuses.is_some_and(|statuses| statuses.is_stunned());
+        // mobs have no mana to pay for anything else
+        if stunned || ability.cost > 0 || !cooldowns.is_ready(ai.ability, &tick) {
+            continue;
+        }
+        let cooldown = statuses.map_or(ability.cooldown_ticks, |statuses| {
+            statuses.cooldown(ability.cooldown_ticks)
+        });
+        cooldowns.start(ai.ability, cooldown, &tick);
+        combat_event.send(CombatEvent::new(entity, target, ai.ability));
+    }
+}
+
+/// Walks back home after leashing and heals up there.
+#[allow(clippy::type_complexity)]
+pub fn return_home(
+    mut commands: Commands,
+    mut mobs: Query<
+        (
+            Entity,
+            &mut MobAi,
+            &mut Tile,
+            &mut Health,
+            Option<&Stats>,
+            Option<&StatusEffects>,
+        ),
+        With<Return>,
+    >,
+    grid: Res<NavGrid>,
+    tick: Res<Tick>,
+) {
+    for (entity, mut ai, mut tile, mut health, stats, statuses) in mobs.iter_mut() {
+        if *tile != ai.home {
+            if !ai.step_ready(statuses, &tick) {
+                continue;
+            }
+            // with the way home blocked it settles down where it is
+            if let Some(next) = grid
+                .find_path(*tile, ai.home, false)
+                .and_then(|path| path.first().copied())
+            {
+                *tile = next;
+                continue;
+            }
+        }
+        if let Some(stats) = stats {
+            if health.hp < stats.max_hp {
+                health.hp = stats.max_hp;
+            }
+        }
+        commands.entity(entity).insert(Done::Success);
+    }
+}
@@ -0,0 +1,389 @@
+use bevy::prelude::*;
+use lib::{
+    abilities::{Abilities, AbilityId, CoolDowns, Effect},
+    archetypes::Archetypes,
+    combat::{DamageEvent, DeathEvent, Mana, Stats},
+    components::{CombatState, EntityType, Health, LeftClick, Target, Tile},
+    nav::NavGrid,
+    resources::Tick,
+    status::{StatusEffects, StatusKind, STATUS_PERIOD_TICKS},
+};
+use rand::Rng;
+
+use crate::{pathing::WalkPath, threat::ThreatTable, world::SpawnPoints};
+
+/// Ticks between regenerating a point of mana.
+pub const MANA_REGEN_TICKS: u64 = 10;
+
+/// An ability going off, its effects are applied in combat_events.
+#[derive(Debug)]
+pub struct CombatEvent {
+    pub ability: AbilityId,
+    pub attacker: Entity,
+    pub target: Entity,
+}
+
+impl CombatEvent {
+    pub fn new(attacker: Entity, target: Entity, ability: AbilityId) -> Self {
+        Self {
+            ability,
+            attacker,
+            target,
+        }
+    }
+}
+
+/// An ability a player asked to use with input `sequence`. The player
+/// walks into range first, a click sent after it cancels it.
+#[derive(Component, Debug)]
+pub struct AbilityIntent {
+    pub ability: AbilityId,
+    pub target: Entity,
+    pub sequence: u64,
+}
+
+/// An ability being cast, it goes off on tick `done` unless a click or
+/// another ability interrupts it.
+#[derive(Component, Debug)]
+pub struct Casting {
+    pub ability: AbilityId,
+    pub target: Entity,
+    pub sequence: u64,
+    pub done: u64,
+}
+
+/// Starts abilities once the target is in range, the cooldown is up and
+/// there's mana for them, until then the player is walked into range.
+/// The walk is acked under the intent's sequence so the client can
+/// predict it.
+#[allow(clippy::type_complexity)]
+pub fn use_abilities(
+    mut commands: Commands,
+    mut casters: Query<(
+        Entity,
+        &AbilityIntent,
+        &Tile,
+        &mut CoolDowns,
+        Option<&mut Mana>,
+        Option<&mut WalkPath>,
+        Option<&Casting>,
+        Option<&StatusEffects>,
+    )>,
+    tiles: Query<&Tile>,
+    abilities: Res<Abilities>,
+    grid: Res<NavGrid>,
+    tick: Res<Tick>,
+    mut combat_event: EventWriter<CombatEvent>,
+) {
+    for (entity, intent, tile, mut cooldowns, mana, path, casting, statuses) in casters.iter_mut() {
+        // using another ability interrupts the cast
+        if casting.is_some() {
+            commands.entity(entity).remove::<Casting>();
+        }
+        if path
+            .as_ref()
+            .is_some_and(|path| path.sequence > intent.sequence)
+        {
+            commands.entity(entity).remove::<AbilityIntent>();
+            continue;
+        }
+        let (Some(ability), Ok(target_tile)) =
+            (abilities.get(intent.ability), tiles.get(intent.target))
+        else {
+            commands.entity(entity).remove::<AbilityIntent>();
+            continue;
+        };
+        let range = ability.range;
+        let steps = if intent.target == entity || range.reaches(tile, target_tile, &grid) {
+            let stunned = statuses.is_some_and(|statuses| statuses.is_stunned());
+            if !stunned && cooldowns.is_ready(intent.ability, &tick) {
+                commands.entity(entity).remove::<AbilityIntent>();
+                let paid = match mana {
+                    Some(mut mana) if mana.mp >= ability.cost => {
+                        mana.mp -= ability.cost;
+                        true
+                    }
+                    None => ability.cost == 0,
+                    _ => false,
+                };
+                // without the mana for it the intent is just dropped
+                if paid {
+                    let cooldown = statuses.map_or(ability.cooldown_ticks, |statuses| {
+                        statuses.cooldown(ability.cooldown_ticks)
+                    });
+                    cooldowns.start(intent.ability, cooldown, &tick);
+                    if ability.cast_ticks == 0 {
+                        commands
+                            .entity(entity)
+                            .insert(CombatState::Punching(tick.tick + 5));
+                        combat_event.send(CombatEvent::new(entity, intent.target, intent.ability));
+                    } else {
+                        commands.entity(entity).insert(Casting {
+                            ability: intent.ability,
+                            target: intent.target,
+                            sequence: intent.sequence,
+                            done: tick.tick + ability.cast_ticks,
+                        });
+                    }
+                }
+            }
+            vec![]
+        } else {
+            // keep walking unless the target moved out of reach of the end
+            if let Some(path) = path.as_ref() {
+                if path.sequence == intent.sequence
+                    && path
+                        .steps
+                        .back()
+                        .is_some_and(|end| range.reaches(end, target_tile, &grid))
+                {
+                    continue;
+                }
+            }
+            range
+                .path_into_range(&grid, *tile, *target_tile)
+                .unwrap_or_else(|| {
+                    commands.entity(entity).remove::<AbilityIntent>();
+                    vec![]
+                })
+        };
+        match path {
+            Some(mut path) => {
+                if path.sequence != intent.sequence {
+                    path.sequence = intent.sequence;
+                    path.taken = 0;
+                }
+                path.steps = steps.into();
+                path.left_click = LeftClick::Walk;
+            }
+            None => {
+                commands.entity(entity).insert(WalkPath {
+                    sequence: intent.sequence,
+                    steps: steps.into(),
+                    taken: 0,
+                    left_click: LeftClick::Walk,
+                });
+            }
+        }
+    }
+}
+
+/// Fires finished casts whose target is still in range, a click sent
+/// after the cast interrupts it.
+pub fn finish_casts(
+    mut commands: Commands,
+    casters: Query<(Entity, &Casting, &Tile, Option<&WalkPath>)>,
+    tiles: Query<&Tile>,
+    abilities: Res<Abilities>,
+    grid: Res<NavGrid>,
+    tick: Res<Tick>,
+    mut combat_event: EventWriter<CombatEvent>,
+) {
+    for (entity, casting, tile, path) in casters.iter() {
+        if path.is_some_and(|path| path.sequence > casting.sequence) {
+            commands.entity(entity).remove::<Casting>();
+            continue;
+        }
+        if tick.tick < casting.done {
+            continue;
+        }
+        commands.entity(entity).remove::<Casting>();
+        let (Some(ability), Ok(target_tile)) =
+            (abilities.get(casting.ability), tiles.get(casting.target))
+        else {
+            continue;
+        };
+        if casting.target == entity || ability.range.reaches(tile, target_tile, &grid) {
+            commands
+                .entity(entity)
+                .insert(CombatState::Punching(tick.tick + 5));
+            combat_event.send(CombatEvent::new(entity, casting.target, casting.ability));
+        }
+    }
+}
+
+pub fn regen_mana(mut mana: Query<&mut Mana>, tick: Res<Tick>) {
+    if tick.tick % MANA_REGEN_TICKS != 0 {
+        return;
+    }
+    for mut mana in mana.iter_mut() {
+        if mana.mp < mana.max {
+            mana.mp += 1;
+        }
+    }
+}
+
+/// Wears off expired effects and applies the damage of Poison and the
+/// healing of Regen every STATUS_PERIOD_TICKS.
+pub fn status_effects(
+    mut affected: Query<(Entity, &mut StatusEffects, &mut Health, Option<&Stats>)>,
+    tick: Res<Tick>,
+    mut damage_event: EventWriter<DamageEvent>,
+    mut death_event: EventWriter<DeathEvent>,
+) {
+    for (entity, mut statuses, mut health, stats) in affected.iter_mut() {
+        // only touch it when something wore off, it's replicated
+        if statuses.has_expired(&tick) {
+            statuses.expire(&tick);
+        }
+        if tick.tick % STATUS_PERIOD_TICKS != 0 || health.hp == 0 {
+            continue;
+        }
+        let regen = statuses.stacks(StatusKind::Regen);
+        if regen > 0 {
+            let max_hp = stats.map_or(health.hp, |stats| stats.max_hp);
+            if health.hp < max_hp {
+                health.hp = health.hp.saturating_add(regen).min(max_hp);
+            }
+        }
+        let poison = statuses.stacks(StatusKind::Poison);
+        if poison > 0 {
+            health.hp = health.hp.saturating_sub(poison);
+            if health.hp == 0 {
+                death_event.send(DeathEvent { entity });
+            }
+            damage_event.send(DamageEvent {
+                entity,
+                attacker: entity,
+                damage: Some(poison),
+            });
+        }
+    }
+}
+
+/// Rolls whether `attacker` hits `defender` and for how much.
+pub fn roll_attack(attacker: &Stats, defender: &Stats, rng: &mut impl Rng) -> Option<u16> {
+    if rng.gen::<f32>() < attacker.hit_chance(defender) {
+        Some(rng.gen_range(1..=attacker.max_hit(defender)))
+    } else {
+        None
+    }
+}
+
+/// Applies the effects of abilities used by players and mobs alike. Every
+/// hit sends a DamageEvent, the one that takes the last hp a DeathEvent.
+#[allow(clippy::too_many_arguments)]
+pub fn combat_events(
+    mut combat_event: EventReader<CombatEvent>,
+    abilities: Res<Abilities>,
+    stats: Query<&Stats>,
+    mut health: Query<&mut Health>,
+    mut statuses: Query<&mut StatusEffects>,
+    mut threats: Query<&mut ThreatTable>,
+    tick: Res<Tick>,
+    mut damage_event: EventWriter<DamageEvent>,
+    mut death_event: EventWriter<DeathEvent>,
+) {
+    let mut rng = rand::thread_rng();
+    for event in combat_event.iter() {
+        let Some(ability) = abilities.get(event.ability) else {
+            continue;
+        };
+        for effect in ability.effects.iter() {
+            let Ok(mut target_health) = health.get_mut(event.target) else {
+                break;
+            };
+            // already dead this tick
+            if target_health.hp == 0 {
+                break;
+            }
+            let damage = match effect {
+                Effect::Attack => {
+                    let (Ok(attacker), Ok(defender)) =
+                        (stats.get(event.attacker), stats.get(event.target))
+                    else {
+                        continue;
+                    };
+                    roll_attack(attacker, defender, &mut rng)
+                }
+                Effect::Damage(damage) => Some(*damage),
+                Effect::Heal(heal) => {
+                    let max_hp = stats
+                        .get(event.target)
+                        .map_or(target_health.hp, |stats| stats.max_hp);
+                    target_health.hp = target_health.hp.saturating_add(*heal).min(max_hp);
+                    continue;
+                }
+                Effect::Status(kind, ticks) => {
+                    if let Ok(mut target_statuses) = statuses.get_mut(event.target) {
+                        target_statuses.apply(*kind, *ticks, &tick);
+                    }
+                    continue;
+                }
+                Effect::Taunt(threat) => {
+                    if let Ok(mut table) = threats.get_mut(event.target) {
+                        table.taunt(event.attacker, *threat);
+                    }
+                    continue;
+                }
+            };
+            let damage = damage.map(|damage| {
+                statuses
+                    .get(event.attacker)
+                    .map_or(damage, |attacker| attacker.damage(damage))
+            });
+            if let Some(damage) = damage {
+                target_health.hp = target_health.hp.saturating_sub(damage);
+                if target_health.hp == 0 {
+                    death_event.send(DeathEvent {
+                        entity: event.target,
+                    });
+                }
+            }
+            damage_event.send(DamageEvent {
+                entity: event.target,
+                attacker: event.attacker,
+                damage,
+            });
+        }
+    }
+}
+
+/// Despawns dead mobs, entered_left_scope sends the despawn to the clients
+/// that saw them. Players respawn on a spawn point with full health,
+/// immortal archetypes like the training dummy just heal up. Whoever
+/// targeted the dead stops and mobs forget their threat.
+pub fn deaths(
+    mut commands: Commands,
+    mut death_event: EventReader<DeathEvent>,
+    mut dead: Query<(&EntityType, &mut Health, &mut Tile, Option<&Stats>)>,
+    mut targets: Query<(&mut Target, Option<&mut CombatState>)>,
+    mut threats: Query<&mut ThreatTable>,
+    spawn_points: Res<SpawnPoints>,
+    archetypes: Res<Archetypes>,
+) {
+    for event in death_event.iter() {
+        for (mut target, combat_state) in targets.iter_mut() {
+            if target.0 == Some(event.entity) {
+                target.0 = None;
+                if let Some(mut combat_state) = combat_state {
+                    *combat_state = CombatState::Idle;
+                }
+            }
+        }
+        for mut table in threats.iter_mut() {
+            table.threat.remove(&event.entity);
+        }
+        let Ok((entity_type, mut health, mut tile, stats)) = dead.get_mut(event.entity) else {
+            continue;
+        };
+        let max_hp = stats.map_or(health.hp, |stats| stats.max_hp);
+        match entity_type {
+            EntityType::Player(_) => {
+                health.hp = max_hp;
+                *tile = spawn_points.pick();
+                commands
+                    .entity(event.entity)
+                    .remove::<(WalkPath, AbilityIntent, Casting)>()
+                    .insert((Target(None), CombatState::Idle, StatusEffects::default()));
+            }
+            _ if archetypes
+                .of(entity_type)
+                .is_some_and(|archetype| archetype.immortal) =>
+            {
+                health.hp = max_hp
+            }
+            _ => commands.entity(event.entity).despawn_recursive(),
+        }
+    }
+}
@@ -2,32 +2,57 @@ use bevy::{prelude::*, utils::HashSet};
 use bevy_renet::renet::{
     RenetConnectionConfig, RenetServer, ServerAuthentication, ServerConfig, ServerEvent,
 };
-use lib::components::{Client, CoolDowns, EntityType, Health, ServerMessages, SpawnEvent, Target, CombatState};
-use lib::PROTOCOL_ID;
+use lib::components::{Client, CombatState, EntityType, Health, ServerMessages, Target};
+use lib::{
+    abilities::CoolDowns,
+    auth::UserData,
+    combat::{Mana, Stats},
+    config::NetConfig,
+    equipment::{BaseStats, Equipment},
+    items::Inventory,
+    status::StatusEffects,
+};
 use lib::{
     channels::{ClientChannel, ServerChannel},
     components::{Player, Scope, Tile},
 };
-use rand::Rng;
 use seldom_state::prelude::*;
 use std::{net::UdpSocket, time::SystemTime};
 
 use crate::{
-    events::ChunkRequest,
+    interest::Interest,
     resources::ServerLobby,
     state::{Idle, Moving, Running},
+    world::SpawnPoints,
 };
 
-pub fn new_renet_server() -> RenetServer {
-    let server_addr = "127.0.0.1:5000".parse().unwrap();
-    let socket = UdpSocket::bind(server_addr).unwrap();
+pub fn new_renet_server(config: &NetConfig) -> Result<RenetServer, String> {
+    let bind_addr = config.bind_socket_addr();
+    let socket = UdpSocket::bind(bind_addr).map_err(|err| format!("binding {bind_addr}: {err}"))?;
+    renet_server(config, socket)
+}
+
+/// A server on an already bound socket, `config.public_socket_addr()` has
+/// to be where clients reach it. Secure mode fails without a private key.
+pub fn renet_server(config: &NetConfig, socket: UdpSocket) -> Result<RenetServer, String> {
     let connection_config = server_connection_config();
-    let server_config =
-        ServerConfig::new(64, PROTOCOL_ID, server_addr, ServerAuthentication::Unsecure);
+    let authentication = if config.secure {
+        let private_key = config.private_key()?;
+        ServerAuthentication::Secure { private_key }
+    } else {
+        ServerAuthentication::Unsecure
+    };
+    let server_config = ServerConfig::new(
+        config.max_clients,
+        config.protocol_id,
+        config.public_socket_addr(),
+        authentication,
+    );
     let current_time = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
-    RenetServer::new(current_time, server_config, connection_config, socket).unwrap()
+    RenetServer::new(current_time, server_config, connection_config, socket)
+        .map_err(|err| format!("starting the server: {err}"))
 }
 
 pub fn server_connection_config() -> RenetConnectionConfig {
@@ -43,19 +68,26 @@ pub fn client_handler(
     mut commands: Commands,
     //mut events: ResMut<Events<ServerEvent>>,
     mut events: EventReader<ServerEvent>,
-    mut request_event: EventWriter<ChunkRequest>,
     mut server: ResMut<RenetServer>,
+    clients: Query<(Entity, &Client)>,
+    spawn_points: Res<SpawnPoints>,
 ) {
     for event in events.iter() {
         match event {
-            ServerEvent::ClientConnected(id, _) => {
-                println!("client connected {}", id);
-                let mut rng = rand::thread_rng();
-                let x: u32 = rng.gen_range(0..10);
+            ServerEvent::ClientConnected(id, user_data) => {
+                // only vouched for by the auth service in secure mode
+                let UserData { account_id, name } = UserData::from_bytes(user_data);
+                println!("client connected {} (account {} {})", id, account_id, name);
+                if server_lobby.accounts.contains_key(&account_id) {
+                    println!("refused client {id}, account {account_id} is already connected");
+                    server.disconnect(*id);
+                    continue;
+                }
+                server_lobby.accounts.insert(account_id, *id);
                 let player = commands
                     .spawn((
                         EntityType::Player(Player { id: *id }),
-                        Tile { cell: (x, 0, 4) },
+                        spawn_points.pick(),
                         StateMachine::new(Idle)
                             .trans::<Idle>(Moving, Running)
                             .insert_on_enter::<Running>(Running)
@@ -66,8 +98,14 @@ pub fn client_handler(
                         Player { id: *id },
                         Target(None),
                         Health { hp: 50 },
+                        Stats::new(10, 5, 50),
+                        BaseStats(Stats::new(10, 5, 50)),
+                        Mana::new(30),
+                        StatusEffects::default(),
                         CoolDowns::default(),
                         CombatState::Idle,
+                        Inventory::default(),
+                        Equipment::default(),
                     ))
                     .id();
                 let new_client = Client {
@@ -76,8 +114,7 @@ pub fn client_handler(
                     scoped_entities: HashSet::new(),
                     controlled_entity: player,
                 };
-                commands.spawn(new_client);
-                request_event.send(ChunkRequest(*id));
+                commands.spawn((new_client, Interest::default()));
                 let new_client = Client {
                     id: *id,
                     scope: Scope::get(Tile { cell: (0, 0, 0) }),
@@ -85,40 +122,26 @@ pub fn client_handler(
                     controlled_entity: player,
                 };
                 server_lobby.clients.insert(*id, new_client);
-                let message = ServerMessages::PlayerConnected { id: *id };
+                let message = ServerMessages::PlayerConnected { id: *id, name };
                 let message = bincode::serialize(&message).unwrap();
                 server.broadcast_message(ServerChannel::ServerMessages, message);
-                let message = SpawnEvent {
-                    entity: player,
-                    entity_type: EntityType::Player(Player { id: *id }),
-                    tile: Tile { cell: (x, 0, 0) },
-                };
-                let message = bincode::serialize(&message).unwrap();
-                server.broadcast_message(ServerChannel::Spawn, message);
             }
 
             ServerEvent::ClientDisconnected(id) => {
                 println!("client disconnected {}", id);
+                server_lobby.accounts.retain(|_, client| client != id);
+                // entered_left_scope sends the despawn to everyone who saw it
                 if let Some((_, client_entity)) = server_lobby.clients.remove_entry(id) {
                     commands
                         .entity(client_entity.controlled_entity)
                         .despawn_recursive();
-                    let message = bincode::serialize(&id).unwrap();
-                    server.broadcast_message(ServerChannel::Despawn, message);
+                }
+                for (entity, client) in clients.iter() {
+                    if client.id == *id {
+                        commands.entity(entity).despawn();
+                    }
                 }
             }
         }
     }
 }
-
-pub fn spawn_player(
-    mut server: ResMut<RenetServer>,
-    new_player: Query<(Entity, &EntityType, &Tile), Added<EntityType>>,
-) {
-    for (entity, player, tile) in &new_player {
-        let message: (Entity, EntityType, Tile) = (entity, *player, *tile);
-        let message = bincode::serialize(&message).unwrap();
-        server.broadcast_message(ServerChannel::Spawn, message);
-        println!("sent spawn message for new player");
-    }
-}
@@ -0,0 +1,374 @@
+//! Generated dungeons. Every floor is split into rooms by binary space
+//! partitioning, each split wall gets an arch with a door so all rooms are
+//! connected, then the rooms get spawners, loot and stairs to the next
+//! floor. The same seed and config always give the same map, the seed is
+//! printed so a layout from a bug report can be made again.
+
+use std::path::Path;
+
+use bevy::utils::HashSet;
+use lib::components::Tile;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::MobRange,
+    spawner::{MobSpawner, SpawnEntry},
+    world::{Floor, Glyph, Map, PlacedItem, PlacedStairs},
+};
+
+/// Read when it exists, like the map file.
+pub const DUNGEON_FILE: &str = "server/data/dungeon.ron";
+
+/// `count` of `item`, one of a room's possible loot.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Loot {
+    pub item: String,
+    pub count: u16,
+}
+
+/// How dungeons are generated, see `server/data/dungeon.ron`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DungeonConfig {
+    /// Tiles along x and z of every floor, outer walls included.
+    pub size: (u32, u32),
+    pub floors: u32,
+    /// The least tiles across a room's floor, at least 3 so arches fit.
+    pub min_room: u32,
+    /// Rooms wider than this are always split further.
+    pub max_room: u32,
+    /// Every floor further down adds one to the levels.
+    pub spawn_table: Vec<SpawnEntry>,
+    pub mobs_per_room: usize,
+    pub respawn_ticks: u64,
+    pub loot: Vec<Loot>,
+    /// The chance of a room having loot in it.
+    pub loot_chance: f64,
+}
+
+impl DungeonConfig {
+    pub fn from_ron(ron: &str) -> Result<Self, String> {
+        let config: Self =
+            ron::from_str(ron).map_err(|err| format!("parsing dungeon config: {err}"))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let ron = std::fs::read_to_string(path)
+            .map_err(|err| format!("reading {}: {err}", path.display()))?;
+        Self::from_ron(&ron)
+    }
+
+    /// DUNGEON_FILE if it's there, the copy built into the binary if not.
+    pub fn load() -> Result<Self, String> {
+        if Path::new(DUNGEON_FILE).exists() {
+            return Self::from_file(DUNGEON_FILE);
+        }
+        Self::from_ron(include_str!("../data/dungeon.ron"))
+    }
+
+    /// `generate` needs at least one floor with room for a room.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.floors == 0 {
+            return Err("a dungeon needs at least one floor".to_string());
+        }
+        if self.min_room < 3 {
+            return Err(format!(
+                "min_room is {}, arches need at least 3",
+                self.min_room
+            ));
+        }
+        if self.max_room < self.min_room {
+            return Err(format!(
+                "max_room {} is less than min_room {}",
+                self.max_room, self.min_room
+            ));
+        }
+        // the outer walls around a single room
+        let least = self.min_room.saturating_add(2);
+        if self.size.0 < least || self.size.1 < least {
+            return Err(format!(
+                "size {:?} is too small for a room of min_room {} and its walls",
+                self.size, self.min_room
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.loot_chance) {
+            return Err(format!(
+                "loot_chance {} isn't between 0 and 1",
+                self.loot_chance
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The copy built into the binary, `load` reads DUNGEON_FILE.
+impl Default for DungeonConfig {
+    fn default() -> Self {
+        Self::from_ron(include_str!("../data/dungeon.ron")).unwrap()
+    }
+}
+
+/// A room or a part of the floor still to be split, the bounds are its
+/// walls.
+#[derive(Copy, Clone, Debug)]
+struct Area {
+    x0: u32,
+    z0: u32,
+    x1: u32,
+    z1: u32,
+}
+
+impl Area {
+    fn interior(&self, y: u32) -> MobRange {
+        MobRange {
+            top_left: Tile::new((self.x0 + 1, y, self.z0 + 1)),
+            bottom_right: Tile::new((self.x1 - 1, y, self.z1 - 1)),
+        }
+    }
+}
+
+/// The glyphs of a floor, `grid[x][z]`.
+type Grid = Vec<Vec<Glyph>>;
+
+pub fn generate(seed: u64, config: &DungeonConfig) -> Map {
+    println!("generating a dungeon from seed {seed}");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let min_room = config.min_room.max(3);
+    let mut map = Map {
+        floors: vec![],
+        spawners: vec![],
+        items: vec![],
+        stairs: vec![],
+    };
+    // stairs, their arrival tiles and the spawn point stay clear
+    let mut taken = HashSet::new();
+    let mut last_rooms: Vec<Area> = vec![];
+    for y in 0..config.floors.max(1) {
+        let (width, depth) = config.size;
+        let mut grid = vec![vec![Glyph::Floor; depth as usize]; width as usize];
+        let bounds = Area {
+            x0: 0,
+            z0: 0,
+            x1: width - 1,
+            z1: depth - 1,
+        };
+        for x in 0..width {
+            grid[x as usize][0] = Glyph::Wall;
+            grid[x as usize][bounds.z1 as usize] = Glyph::Wall;
+        }
+        for z in 0..depth {
+            grid[0][z as usize] = Glyph::Wall;
+            grid[bounds.x1 as usize][z as usize] = Glyph::Wall;
+        }
+        let mut rooms = vec![];
+        split(&mut rng, config, min_room, &mut grid, bounds, &mut rooms);
+
+        let first = if y == 0 {
+            let room = rooms[0];
+            let spawn = Tile::new(((room.x0 + room.x1) / 2, y, (room.z0 + room.z1) / 2));
+            grid[spawn.cell.0 as usize][spawn.cell.2 as usize] = Glyph::SpawnPoint;
+            taken.insert(spawn);
+            1
+        } else {
+            // the way back up, next to where the stairs down arrive
+            let down = last_rooms.choose(&mut rng).copied().unwrap();
+            let up = rooms.choose(&mut rng).copied().unwrap();
+            if let (Some((down, down_arrival)), Some((up, up_arrival))) = (
+                free_pair(&mut rng, &down, y - 1, &taken),
+                free_pair(&mut rng, &up, y, &taken),
+            ) {
+                taken.extend([down, down_arrival, up, up_arrival]);
+                map.stairs.push(PlacedStairs {
+                    tile: down,
+                    to: up_arrival,
+                });
+                map.stairs.push(PlacedStairs {
+                    tile: up,
+                    to: down_arrival,
+                });
+            }
+            0
+        };
+        for room in rooms.iter().skip(first) {
+            if config.mobs_per_room > 0 && !config.spawn_table.is_empty() {
+                map.spawners.push(MobSpawner {
+                    table: config
+                        .spawn_table
+                        .iter()
+                        .map(|entry| SpawnEntry {
+                            min_level: entry.min_level + y as u16,
+                            max_level: entry.max_level + y as u16,
+                            ..entry.clone()
+                        })
+                        .collect(),
+                    max_alive: config.mobs_per_room,
+                    respawn_ticks: config.respawn_ticks,
+                    area: room.interior(y),
+                    respawn_at: None,
+                });
+            }
+            if !rng.gen_bool(config.loot_chance.clamp(0.0, 1.0)) {
+                continue;
+            }
+            let (Some(loot), Some(tile)) = (
+                config.loot.choose(&mut rng),
+                free_tile(&mut rng, room, y, &taken),
+            ) else {
+                continue;
+            };
+            taken.insert(tile);
+            map.items.push(PlacedItem {
+                item: loot.item.clone(),
+                count: loot.count,
+                tile,
+            });
+        }
+        map.floors.push(Floor {
+            y,
+            rows: grid
+                .iter()
+                .map(|row| row.iter().map(|glyph| glyph.to_char()).collect())
+                .collect(),
+        });
+        last_rooms = rooms;
+    }
+    map
+}
+
+/// Splits `area` in two with a wall until the parts are rooms, then
+/// connects the two sides of the wall.
+fn split(
+    rng: &mut StdRng,
+    config: &DungeonConfig,
+    min_room: u32,
+    grid: &mut Grid,
+    area: Area,
+    rooms: &mut Vec<Area>,
+) {
+    let width = area.x1 - area.x0 - 1;
+    let depth = area.z1 - area.z0 - 1;
+    // both sides of the wall need min_room tiles
+    let (split_x, split_z) = (width > 2 * min_room, depth > 2 * min_room);
+    let too_big = width > config.max_room || depth > config.max_room;
+    if !(split_x || split_z) || (!too_big && rng.gen_bool(0.3)) {
+        rooms.push(area);
+        return;
+    }
+    let along_x = match (split_x, split_z) {
+        (true, true) if width == depth => rng.gen(),
+        (true, true) => width > depth,
+        (split_x, _) => split_x,
+    };
+    if along_x {
+        let wall = rng.gen_range(area.x0 + 1 + min_room..area.x1 - min_room);
+        for z in area.z0..=area.z1 {
+            grid[wall as usize][z as usize] = Glyph::Wall;
+        }
+        split(
+            rng,
+            config,
+            min_room,
+            grid,
+            Area { x1: wall, ..area },
+            rooms,
+        );
+        split(
+            rng,
+            config,
+            min_room,
+            grid,
+            Area { x0: wall, ..area },
+            rooms,
+        );
+        let tiles = (area.z0..=area.z1).map(|z| (wall, z)).collect();
+        connect(rng, grid, tiles, (1, 0), Glyph::ArchAlongZ { door: true });
+    } else {
+        let wall = rng.gen_range(area.z0 + 1 + min_room..area.z1 - min_room);
+        for x in area.x0..=area.x1 {
+            grid[x as usize][wall as usize] = Glyph::Wall;
+        }
+        split(
+            rng,
+            config,
+            min_room,
+            grid,
+            Area { z1: wall, ..area },
+            rooms,
+        );
+        split(
+            rng,
+            config,
+            min_room,
+            grid,
+            Area { z0: wall, ..area },
+            rooms,
+        );
+        let tiles = (area.x0..=area.x1).map(|x| (x, wall)).collect();
+        connect(rng, grid, tiles, (0, 1), Glyph::ArchAlongX { door: true });
+    }
+}
+
+/// Hangs an arch with a door in three wall `tiles` in a row that have
+/// floor on both sides, `across` is the step to those sides. Walls split
+/// again on both sides may leave no such place, then a single tile of it
+/// is opened up instead.
+fn connect(
+    rng: &mut StdRng,
+    grid: &mut Grid,
+    tiles: Vec<(u32, u32)>,
+    across: (u32, u32),
+    arch: Glyph,
+) {
+    let open = |grid: &Grid, (x, z): (u32, u32)| {
+        grid[x as usize][z as usize] == Glyph::Wall
+            && grid[(x - across.0) as usize][(z - across.1) as usize] == Glyph::Floor
+            && grid[(x + across.0) as usize][(z + across.1) as usize] == Glyph::Floor
+    };
+    let arches: Vec<usize> = (0..tiles.len().saturating_sub(2))
+        .filter(|start| {
+            tiles[*start..*start + 3]
+                .iter()
+                .all(|tile| open(grid, *tile))
+        })
+        .collect();
+    if let Some(start) = arches.choose(rng) {
+        let (x, z) = tiles[*start];
+        grid[x as usize][z as usize] = arch;
+        for (x, z) in &tiles[start + 1..start + 3] {
+            grid[*x as usize][*z as usize] = Glyph::Floor;
+        }
+        return;
+    }
+    let gaps: Vec<&(u32, u32)> = tiles.iter().filter(|tile| open(grid, **tile)).collect();
+    if let Some((x, z)) = gaps.choose(rng) {
+        grid[*x as usize][*z as usize] = Glyph::Floor;
+    }
+}
+
+/// A random floor tile of `room` nobody else uses.
+fn free_tile(rng: &mut StdRng, room: &Area, y: u32, taken: &HashSet<Tile>) -> Option<Tile> {
+    let range = room.interior(y);
+    let tiles: Vec<Tile> = (range.top_left.cell.0..=range.bottom_right.cell.0)
+        .flat_map(|x| {
+            (range.top_left.cell.2..=range.bottom_right.cell.2).map(move |z| Tile::new((x, y, z)))
+        })
+        .filter(|tile| !taken.contains(tile))
+        .collect();
+    tiles.choose(rng).copied()
+}
+
+/// Stairs in `room` and the free tile next to them players arrive on.
+fn free_pair(rng: &mut StdRng, room: &Area, y: u32, taken: &HashSet<Tile>) -> Option<(Tile, Tile)> {
+    let range = room.interior(y);
+    let pairs: Vec<(Tile, Tile)> = (range.top_left.cell.0..range.bottom_right.cell.0)
+        .flat_map(|x| {
+            (range.top_left.cell.2..=range.bottom_right.cell.2)
+                .map(move |z| (Tile::new((x, y, z)), Tile::new((x + 1, y, z))))
+        })
+        .filter(|(stairs, arrival)| !taken.contains(stairs) && !taken.contains(arrival))
+        .collect();
+    pairs.choose(rng).copied()
+}
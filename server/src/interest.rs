@@ -0,0 +1,100 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use lib::components::{EntityType, Scope, Tile};
+
+/// Side length in tiles of an interest chunk. Clients see whole chunks, so
+/// scope changes only happen when a player crosses a chunk boundary.
+pub const INTEREST_CHUNK: u32 = 8;
+
+pub type ChunkKey = (u32, u32, u32);
+
+pub fn chunk_key(tile: &Tile) -> ChunkKey {
+    let (x, y, z) = tile.cell;
+    (x / INTEREST_CHUNK, y, z / INTEREST_CHUNK)
+}
+
+/// Every chunk that overlaps `scope`, on the floors between `down` and `up`.
+pub fn observed_chunks(scope: &Scope) -> HashSet<ChunkKey> {
+    let (min_x, _, min_z) = chunk_key(&scope.bottom_right);
+    let (max_x, _, max_z) = chunk_key(&scope.top_left);
+    let mut chunks = HashSet::new();
+    for y in scope.down.cell.1..=scope.up.cell.1 {
+        for x in min_x..=max_x {
+            for z in min_z..=max_z {
+                chunks.insert((x, y, z));
+            }
+        }
+    }
+    chunks
+}
+
+/// Which entities are in which chunk. `moved` collects the entities that
+/// changed chunk, spawned or despawned since entered_left_scope last ran.
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+    chunks: HashMap<ChunkKey, HashSet<Entity>>,
+    positions: HashMap<Entity, ChunkKey>,
+    pub moved: HashSet<Entity>,
+}
+
+impl SpatialIndex {
+    pub fn chunk_of(&self, entity: Entity) -> Option<ChunkKey> {
+        self.positions.get(&entity).copied()
+    }
+
+    pub fn entities(&self, chunk: &ChunkKey) -> impl Iterator<Item = &Entity> {
+        self.chunks.get(chunk).into_iter().flatten()
+    }
+
+    fn insert(&mut self, entity: Entity, chunk: ChunkKey) {
+        if self.positions.get(&entity) == Some(&chunk) {
+            return;
+        }
+        self.remove(entity);
+        self.chunks.entry(chunk).or_default().insert(entity);
+        self.positions.insert(entity, chunk);
+        self.moved.insert(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some(chunk) = self.positions.remove(&entity) else {
+            return;
+        };
+        if let Some(entities) = self.chunks.get_mut(&chunk) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                self.chunks.remove(&chunk);
+            }
+        }
+        self.moved.insert(entity);
+    }
+}
+
+/// The chunks a client currently sees.
+#[derive(Component, Default)]
+pub struct Interest {
+    pub chunks: HashSet<ChunkKey>,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    changed: Query<(Entity, &Tile), (Changed<Tile>, With<EntityType>)>,
+) {
+    for (entity, tile) in changed.iter() {
+        index.insert(entity, chunk_key(tile));
+    }
+}
+
+/// Runs every frame rather than on the tick, removed components are only
+/// kept around for a couple of frames.
+pub fn remove_despawned(
+    mut index: ResMut<SpatialIndex>,
+    mut despawned: RemovedComponents<EntityType>,
+) {
+    for entity in despawned.iter() {
+        index.remove(entity);
+    }
+}
@@ -0,0 +1,150 @@
+//! Player inventories and equipment. Items lie on the ground as entities
+//! until a player standing on or next to them picks them up, the inventory
+//! itself is only sent to the player it belongs to.
+
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use lib::{
+    channels::ServerChannel,
+    combat::Stats,
+    components::{EntityType, Health, LeftClick, Player, PlayerCommand, Tile},
+    equipment::{BaseStats, Equipment},
+    items::{Inventory, InventoryUpdate, ItemStack, Items},
+    nav::is_adjacent,
+    resources::Tick,
+    ServerEvents,
+};
+
+use crate::{resources::ServerLobby, world::Map, InteractEvent};
+
+/// An inventory command from `player`.
+#[derive(Debug)]
+pub struct InventoryEvent {
+    pub player: Entity,
+    pub command: PlayerCommand<Entity>,
+}
+
+/// Puts `stack` on the ground at `tile`.
+pub fn spawn_item(commands: &mut Commands, stack: ItemStack, tile: Tile) -> Entity {
+    commands
+        .spawn((EntityType::Item(stack.item), stack, tile))
+        .id()
+}
+
+/// Puts the items of the map on the ground.
+pub fn place_items(mut commands: Commands, map: Res<Map>, items: Res<Items>) {
+    for placed in map.items.iter() {
+        let Some(item) = items.id(&placed.item) else {
+            warn!("the map places unknown item {:?}", placed.item);
+            continue;
+        };
+        spawn_item(
+            &mut commands,
+            ItemStack::new(item, placed.count),
+            placed.tile,
+        );
+    }
+}
+
+/// Picks up the clicked item once the player reached it. Whatever doesn't
+/// fit stays on the ground.
+pub fn pick_up_items(
+    mut commands: Commands,
+    lobby: Res<ServerLobby>,
+    items: Res<Items>,
+    mut interact_event: EventReader<InteractEvent>,
+    mut players: Query<(&Tile, &mut Inventory)>,
+    mut ground: Query<(&Tile, &mut ItemStack), Without<Inventory>>,
+) {
+    for event in interact_event.iter() {
+        let LeftClick::Pickup(Some(item)) = event.left_click else {
+            continue;
+        };
+        let Some(client) = lobby.clients.get(&event.client_id) else {
+            continue;
+        };
+        let (Ok((tile, mut inventory)), Ok((item_tile, mut stack))) = (
+            players.get_mut(client.controlled_entity),
+            ground.get_mut(item),
+        ) else {
+            continue;
+        };
+        // the despawn waits for the end of the tick, someone else may have
+        // emptied the stack already
+        if stack.count == 0 || (tile != item_tile && !is_adjacent(tile, item_tile)) {
+            continue;
+        }
+        let left = inventory.add(*stack, &items);
+        stack.count = left;
+        if left == 0 {
+            commands.entity(item).despawn_recursive();
+        }
+    }
+}
+
+/// Drops, moves, splits and equips stacks. Out of range slots are ignored.
+pub fn inventory_commands(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut inventory_event: EventReader<InventoryEvent>,
+    mut players: Query<(&Tile, &mut Inventory, &mut Equipment)>,
+) {
+    for event in inventory_event.iter() {
+        let Ok((tile, mut inventory, mut equipment)) = players.get_mut(event.player) else {
+            continue;
+        };
+        match event.command {
+            PlayerCommand::DropItem(slot) => {
+                if let Some(stack) = inventory.take(slot as usize) {
+                    spawn_item(&mut commands, stack, *tile);
+                }
+            }
+            PlayerCommand::MoveItem(from, to) => {
+                inventory.move_stack(from as usize, to as usize, &items);
+            }
+            PlayerCommand::SplitStack(slot, count) => {
+                inventory.split(slot as usize, count);
+            }
+            PlayerCommand::Equip(slot) => {
+                equipment.equip(&mut inventory, slot as usize, &items);
+            }
+            PlayerCommand::Unequip(slot) => {
+                equipment.unequip(&mut inventory, slot, &items);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Adds the equipped bonuses to the base stats, health stays within the
+/// new max_hp.
+pub fn apply_equipment(
+    mut players: Query<(&Equipment, &BaseStats, &mut Stats, &mut Health), Changed<Equipment>>,
+    items: Res<Items>,
+) {
+    for (equipment, base, mut stats, mut health) in players.iter_mut() {
+        let equipped = equipment.stats(&base.0, &items);
+        if *stats != equipped {
+            *stats = equipped;
+        }
+        if health.hp > stats.max_hp {
+            health.hp = stats.max_hp;
+        }
+    }
+}
+
+/// Sends changed inventories to their owners, and only to them.
+pub fn send_inventories(
+    players: Query<(&Player, &Inventory), Changed<Inventory>>,
+    tick: Res<Tick>,
+    mut server: ResMut<RenetServer>,
+) {
+    for (player, inventory) in players.iter() {
+        let update = InventoryUpdate {
+            tick: tick.tick,
+            inventory: inventory.clone(),
+        };
+        let message = bincode::serialize(&vec![ServerEvents::Inventory(update)]).unwrap();
+        server.send_message(player.id, ServerChannel::ServerEvents, message);
+    }
+}
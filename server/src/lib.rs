@@ -0,0 +1,202 @@
+use ai::{acquire_targets, aggro, attack, chase, idle, return_home, wander, MobAiPlugin};
+use bevy::prelude::*;
+use bevy_renet::{renet::RenetServer, RenetServerPlugin};
+use combat::{
+    combat_events, deaths, finish_casts, regen_mana, status_effects, use_abilities, CombatEvent,
+};
+use connection::client_handler;
+use events::ClientSetup;
+use interest::{remove_despawned, update_spatial_index, SpatialIndex};
+use inventory::{
+    apply_equipment, inventory_commands, pick_up_items, place_items, send_inventories,
+    InventoryEvent,
+};
+use lib::{
+    abilities::Abilities,
+    archetypes::{mark_blockers, Archetypes},
+    channels::ServerChannel,
+    combat::{DamageEvent, DeathEvent},
+    components::{LeftClick, SpawnEvent, Tile},
+    items::Items,
+    nav::{update_nav_grid, NavGrid},
+    replication::{ReplicationPlugin, ReplicationSet},
+    resources::Tick,
+    TickSet,
+};
+use pathing::{advance_paths, take_stairs};
+use plugins::{ClearEventPlugin, ConfigPlugin};
+use receive::{interact, left_click, message, receive_snapshot_acks, send_input_acks};
+use resources::{ServerLobby, SnapshotHistory};
+use seldom_state::prelude::*;
+use spawner::{place_spawners, spawn_mobs};
+use sync::{
+    assign_net_ids, entered_left_scope, send_damage_event, send_death_event, send_snapshots,
+};
+use threat::{damage_threat, decay_threat, drop_threat, pick_targets};
+use world::load_map;
+
+pub mod ai;
+pub mod combat;
+pub mod connection;
+pub mod dungeon;
+pub mod events;
+pub mod interest;
+pub mod inventory;
+pub mod pathing;
+pub mod plugins;
+pub mod receive;
+pub mod resources;
+pub mod spawner;
+pub mod state;
+pub mod sync;
+pub mod threat;
+pub mod world;
+
+/// Everything the server simulates and syncs, without the transport.
+/// Needs a `RenetServer` resource, the tick rate as `FixedTime` and the
+/// `Map`, see `server.rs`.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(ConfigPlugin);
+        app.add_plugin(ClearEventPlugin);
+        app.add_plugin(StateMachinePlugin);
+        app.add_plugin(MobAiPlugin);
+        app.add_plugin(ReplicationPlugin::server());
+
+        app.insert_resource(Tick::default());
+        app.init_resource::<ServerLobby>();
+        app.init_resource::<SnapshotHistory>();
+        app.init_resource::<SpatialIndex>();
+        app.init_resource::<NavGrid>();
+        app.init_resource::<Events<ClientSetup>>();
+        app.init_resource::<Events<LeftClickEvent>>();
+        app.init_resource::<Events<InteractEvent>>();
+        app.init_resource::<Events<InventoryEvent>>();
+        app.init_resource::<Events<SpawnEvent>>();
+        app.init_resource::<Events<CombatEvent>>();
+        app.init_resource::<Events<DamageEvent>>();
+        app.init_resource::<Events<DeathEvent>>();
+        app.init_resource::<Abilities>();
+        app.init_resource::<Items>();
+        app.init_resource::<Archetypes>();
+        app.add_systems(
+            (tick, send_tick)
+                .chain()
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        app.add_system(remove_despawned);
+        app.add_system(
+            client_handler
+                .in_set(TickSet::Connection)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        // system tuples take at most 15 systems, the chain is split in
+        // the receiving, the player and the combat half
+        app.add_systems(
+            (
+                assign_net_ids,
+                update_spatial_index,
+                entered_left_scope,
+                message,
+                receive_snapshot_acks,
+                mark_blockers,
+                update_nav_grid,
+            )
+                .chain()
+                .before(ReplicationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        app.add_systems(
+            (
+                left_click,
+                finish_casts,
+                use_abilities,
+                advance_paths,
+                take_stairs,
+                interact,
+                pick_up_items,
+                inventory_commands,
+                apply_equipment,
+                send_input_acks,
+            )
+                .chain()
+                .after(update_nav_grid)
+                .before(ReplicationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        app.add_systems(
+            (
+                regen_mana,
+                status_effects,
+                combat_events,
+                deaths,
+                spawn_mobs,
+                send_damage_event,
+                send_death_event,
+            )
+                .chain()
+                .after(send_input_acks)
+                .before(ReplicationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        app.add_systems(
+            (
+                decay_threat,
+                acquire_targets,
+                damage_threat,
+                drop_threat,
+                pick_targets,
+                idle,
+                wander,
+                aggro,
+                chase,
+                attack,
+                return_home,
+            )
+                .chain()
+                .after(client_handler)
+                .after(status_effects)
+                .before(combat_events)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        app.add_systems(
+            (send_snapshots, send_inventories)
+                .after(ReplicationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        app.add_systems(
+            (RenetServerPlugin::get_clear_event_systems().in_set(TickSet::Clear))
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+        app.add_startup_system(load_map);
+        app.add_startup_system(place_spawners);
+        app.add_startup_system(place_items);
+        app.add_event::<ClientSetup>();
+    }
+}
+#[derive(Debug)]
+pub struct LeftClickEvent {
+    pub client_id: u64,
+    pub sequence: u64,
+    pub left_click: LeftClick,
+    pub tile: Tile,
+}
+
+/// Sent when a player reached the target of a click.
+#[derive(Debug)]
+pub struct InteractEvent {
+    pub client_id: u64,
+    pub left_click: LeftClick,
+}
+
+//#[bevycheck::system]
+pub fn tick(mut tick: ResMut<Tick>) {
+    tick.tick += 1;
+}
+pub fn send_tick(mut server: ResMut<RenetServer>, tick: Res<Tick>) {
+    let tick = Tick { tick: tick.tick };
+    let message = bincode::serialize(&tick).unwrap();
+    server.broadcast_message(ServerChannel::Tick, message)
+}
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use lib::{
+    components::{EntityType, LeftClick, Player, Tile},
+    nav::NavGrid,
+    resources::Tick,
+    status::StatusEffects,
+};
+
+use crate::InteractEvent;
+
+/// The path the server is walking a player along, `taken` counts the steps
+/// already walked for input `sequence`. `left_click` fires when it arrives.
+#[derive(Component, Default, Debug)]
+pub struct WalkPath {
+    pub sequence: u64,
+    pub steps: VecDeque<Tile>,
+    pub taken: u32,
+    pub left_click: LeftClick,
+}
+
+/// Moves every walking player one tile per tick, slowed players every
+/// few ticks and stunned ones not at all.
+pub fn advance_paths(
+    mut players: Query<(&Player, &mut Tile, &mut WalkPath, Option<&StatusEffects>)>,
+    grid: Res<NavGrid>,
+    tick: Res<Tick>,
+    mut interact_event: EventWriter<InteractEvent>,
+) {
+    for (player, mut tile, mut path, statuses) in players.iter_mut() {
+        let interval = statuses.map_or(Some(1), |statuses| statuses.move_interval());
+        if !interval.is_some_and(|interval| tick.tick % interval == 0) {
+            continue;
+        }
+        if let Some(next) = path.steps.pop_front() {
+            // a door may have closed since the path was planned
+            if grid.is_walkable(&next) {
+                *tile = next;
+                path.taken += 1;
+            } else {
+                path.steps.clear();
+                path.left_click = LeftClick::Walk;
+            }
+        }
+        if path.steps.is_empty() && path.left_click != LeftClick::Walk {
+            interact_event.send(InteractEvent {
+                client_id: player.id,
+                left_click: path.left_click,
+            });
+            path.left_click = LeftClick::Walk;
+        }
+    }
+}
+
+/// Takes players that stepped onto stairs to the floor they lead to, the
+/// rest of their path was planned on the floor they left.
+#[allow(clippy::type_complexity)]
+pub fn take_stairs(
+    mut players: Query<(&mut Tile, Option<&mut WalkPath>), (With<Player>, Changed<Tile>)>,
+    stairs: Query<(&Tile, &EntityType), Without<Player>>,
+) {
+    for (mut tile, path) in players.iter_mut() {
+        let to = stairs
+            .iter()
+            .find_map(|(stairs_tile, entity_type)| match entity_type {
+                EntityType::Stairs(stairs) if stairs_tile == &*tile => Some(stairs.to),
+                _ => None,
+            });
+        let Some(to) = to else {
+            continue;
+        };
+        *tile = to;
+        if let Some(mut path) = path {
+            path.steps.clear();
+            path.left_click = LeftClick::Walk;
+        }
+    }
+}
@@ -2,10 +2,7 @@ use bevy::prelude::*;
 use bevy_renet::RenetServerPlugin;
 use lib::TickSet;
 
-use crate::{
-    events::{clear_event},
-    LeftClickEvent,
-};
+use crate::{events::clear_event, inventory::InventoryEvent, InteractEvent, LeftClickEvent};
 
 pub struct ConfigPlugin;
 
@@ -33,6 +30,8 @@ impl Plugin for ClearEventPlugin {
             //.in_set(TickSet::Clear),
             //clear_event::<ChunkRequest>.in_base_set(CoreSet::Last),
             clear_event::<LeftClickEvent>.in_base_set(CoreSet::Last),
+            clear_event::<InteractEvent>.in_base_set(CoreSet::Last),
+            clear_event::<InventoryEvent>.in_base_set(CoreSet::Last),
         ));
     }
 }
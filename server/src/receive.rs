@@ -1,56 +1,88 @@
-use bevy::prelude::{
-    Commands, DespawnRecursiveExt, Entity, EventReader, EventWriter, Query, Res, ResMut,
-};
+use bevy::prelude::{Commands, Entity, EventReader, EventWriter, Query, Res, ResMut};
 use bevy_renet::renet::RenetServer;
 use lib::{
+    abilities::{Abilities, AbilityTarget},
     channels::{ClientChannel, ServerChannel},
     components::{
-        Action, CombatState, CoolDowns, EntityType, LeftClick, OpenState, PlayerCommand, Target,
+        EntityType, InputAck, LeftClick, OpenState, PlayerCommand, PlayerInput, Target, Tile,
     },
-    resources::Tick,
-    ClickEvent,
+    nav::NavGrid,
+    net_id::{NetIds, NetMapped},
+    replication::SnapshotAck,
 };
 
-use crate::{resources::ServerLobby, CombatEvent, LeftClickEvent, MobState};
+use crate::{
+    combat::AbilityIntent,
+    inventory::InventoryEvent,
+    pathing::WalkPath,
+    resources::{ServerLobby, SnapshotHistory},
+    InteractEvent, LeftClickEvent,
+};
 
+#[allow(clippy::too_many_arguments)]
 pub fn message(
     mut server: ResMut<RenetServer>,
     _item_query: Query<(Entity, &EntityType)>,
     mut left_click_event: EventWriter<LeftClickEvent>,
-    mut combat_event: EventWriter<CombatEvent>,
-    mut target_query: Query<(&Target, &mut CoolDowns)>,
+    mut inventory_event: EventWriter<InventoryEvent>,
+    target_query: Query<&Target>,
+    tiles: Query<&Tile>,
     lobby: Res<ServerLobby>,
-    tick: Res<Tick>,
+    abilities: Res<Abilities>,
+    ids: Res<NetIds>,
     mut commands: Commands,
 ) {
     for client_id in server.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, ClientChannel::Command) {
-            let command: PlayerCommand = bincode::deserialize(&message).unwrap();
-            //println!("receive  msg {:?}", command);
+            let Ok(input) = bincode::deserialize::<PlayerInput>(&message) else {
+                continue;
+            };
+            let Some(command) = PlayerCommand::from_net(input.command, &ids) else {
+                continue;
+            };
             match command {
                 PlayerCommand::LeftClick(left_click, tile) => {
                     left_click_event.send(LeftClickEvent {
                         client_id,
+                        sequence: input.sequence,
                         left_click,
                         tile,
                     });
                 }
-                //CombatEvent using target: Entity, instead of Target(Entity)
-                PlayerCommand::AutoAttack => {
+                // use_abilities walks into range and starts it once the
+                // cooldown allows
+                PlayerCommand::UseAbility(ability_id, target) => {
+                    let Some(client) = lobby.clients.get(&client_id) else {
+                        continue;
+                    };
+                    let Some(ability) = abilities.get(ability_id) else {
+                        continue;
+                    };
+                    let player = client.controlled_entity;
+                    let target = match ability.target {
+                        AbilityTarget::Caster => Some(player),
+                        AbilityTarget::Enemy => target
+                            .or_else(|| target_query.get(player).ok().and_then(|target| target.0)),
+                    };
+                    if let Some(target) = target.filter(|target| tiles.contains(*target)) {
+                        commands.entity(player).insert(AbilityIntent {
+                            ability: ability_id,
+                            target,
+                            sequence: input.sequence,
+                        });
+                    }
+                }
+                // inventory_commands applies them
+                PlayerCommand::DropItem(_)
+                | PlayerCommand::MoveItem(..)
+                | PlayerCommand::SplitStack(..)
+                | PlayerCommand::Equip(_)
+                | PlayerCommand::Unequip(_) => {
                     if let Some(client) = lobby.clients.get(&client_id) {
-                        if let Ok((target, mut cooldowns)) =
-                            target_query.get_mut(client.controlled_entity)
-                        {
-                            if cooldowns.cd_auto_attack(&tick) {
-                                if let Some(target) = target.0 {
-                                    commands
-                                        .entity(client.controlled_entity)
-                                        .insert(CombatState::Punching(tick.tick + 5));
-                                    combat_event.send(CombatEvent::new(target, Action::AutoAttack));
-                                    println!("received autoattack")
-                                }
-                            }
-                        }
+                        inventory_event.send(InventoryEvent {
+                            player: client.controlled_entity,
+                            command,
+                        });
                     }
                 }
             }
@@ -58,84 +90,114 @@ pub fn message(
     }
 }
 
+/// Plans the path for a click, the player walks it in advance_paths and the
+/// click itself fires as an InteractEvent once it arrives.
 pub fn left_click(
-    mut server: ResMut<RenetServer>,
     mut commands: Commands,
-    lobby: ResMut<ServerLobby>,
+    lobby: Res<ServerLobby>,
+    grid: Res<NavGrid>,
     mut left_click_event: EventReader<LeftClickEvent>,
+    tiles: Query<&Tile>,
 ) {
     for event in left_click_event.iter() {
-        match event.left_click {
-            LeftClick::Walk => {
-                if let Some(client) = lobby.clients.get(&event.client_id) {
-                    //println!("inserted new tile");
-                    commands.entity(client.controlled_entity).insert(event.tile);
-                    //let message = UpdateEvent {
-                    //entity: client.controlled_entity,
-                    //component: ComponentType::Tile(event.tile),
-                    //};
-                    //let serd_message = bincode::serialize(&message).unwrap();
-                    //server.broadcast_message(ServerChannel::Update, serd_message);
-                    //println!("walk");
-                }
-            }
-
-            LeftClick::Pickup(Some(e)) => {
-                //println!("pickup {:?}", e);
-                if let Some(_player_entity) = lobby.clients.get(&event.client_id) {
-                    //commands.entity(*player_entity).insert(tile);
-                    //for (player, mut inventory) in players.iter_mut() {
-                    //if *player_entity == player {
-                    //for (item, item_id) in item_query.iter() {
-                    //if e == item {
-                    //let inventory_item = commands.spawn(*item_id).id();
-                    //inventory.slots.insert(inventory_item);
-                    //println!("inserted into inventory");
-                    //let message = bincode::serialize(item_id).unwrap();
-                    //server.send_message(
-                    //client_id,
-                    //ServerChannel::Test,
-                    //message,
-                    //);
-                    //}
-                    //}
-                    //}
-                    //}
-                    commands.entity(e).despawn_recursive();
-                    //println!("pickup {:?}", e);
+        let Some(client) = lobby.clients.get(&event.client_id) else {
+            continue;
+        };
+        let Ok(start) = tiles.get(client.controlled_entity) else {
+            continue;
+        };
+        // walk towards where the target really is, not the tile the
+        // client sent along
+        let goal = match event.left_click {
+            LeftClick::Attack(e)
+            | LeftClick::Open(e)
+            | LeftClick::Close(e)
+            | LeftClick::Pickup(Some(e)) => match tiles.get(e) {
+                Ok(tile) => *tile,
+                Err(_) => continue,
+            },
+            _ => event.tile,
+        };
+        let adjacent = event.left_click != LeftClick::Walk;
+        if let Some(steps) = grid.find_path(*start, goal, adjacent) {
+            commands.entity(client.controlled_entity).insert(WalkPath {
+                sequence: event.sequence,
+                steps: steps.into(),
+                taken: 0,
+                left_click: event.left_click,
+            });
+        }
+    }
+}
 
-                    let despawn_message = bincode::serialize(&e).unwrap();
-                    server.broadcast_message(ServerChannel::Despawn, despawn_message);
-                }
-            }
+pub fn interact(
+    mut commands: Commands,
+    lobby: Res<ServerLobby>,
+    mut interact_event: EventReader<InteractEvent>,
+    entity_types: Query<&EntityType>,
+) {
+    for event in interact_event.iter() {
+        match event.left_click {
+            // levers don't do anything yet
+            LeftClick::Walk | LeftClick::Pull => (),
+            // pick_up_items handles it, without an item there is nothing to
+            // pick up
+            LeftClick::Pickup(_) => (),
             LeftClick::Attack(e) => {
                 if let Some(client) = lobby.clients.get(&event.client_id) {
-                    println!("inserted target");
                     commands
                         .entity(client.controlled_entity)
                         .insert(Target(Some(e)));
-                    commands
-                        .entity(e)
-                        .insert(MobState::Combat(client.controlled_entity));
                 }
             }
+            // the NavGrid trusts OpenState, only doors get one
+            LeftClick::Open(e) | LeftClick::Close(e)
+                if !matches!(entity_types.get(e), Ok(EntityType::Door(_))) => {}
             LeftClick::Open(e) => {
                 commands.entity(e).insert(OpenState::Open);
             }
             LeftClick::Close(e) => {
                 commands.entity(e).insert(OpenState::Closed);
             }
-            LeftClick::Pull => todo!(),
-            LeftClick::Pickup(_) => todo!(),
-            //_ => {}
         }
     }
 }
-pub fn clicks(lobby: Res<ServerLobby>, mut server: ResMut<RenetServer>) {
-    for (client_id, _) in lobby.clients.iter() {
-        if let Some(message) = server.receive_message(*client_id, ClientChannel::Click) {
-            let click: ClickEvent = bincode::deserialize(&message).unwrap();
-            println!("click event: {:?}", click);
+/// Tells every client where its controlled entity is on the path it is
+/// walking, the client reconciles its prediction against it.
+pub fn send_input_acks(
+    lobby: Res<ServerLobby>,
+    players: Query<(&Tile, Option<&WalkPath>)>,
+    mut server: ResMut<RenetServer>,
+) {
+    for (client_id, client) in lobby.clients.iter() {
+        if let Ok((tile, path)) = players.get(client.controlled_entity) {
+            let ack = InputAck {
+                sequence: path.map_or(0, |path| path.sequence),
+                step: path.map_or(0, |path| path.taken),
+                tile: *tile,
+            };
+            let message = bincode::serialize(&ack).unwrap();
+            server.send_message(*client_id, ServerChannel::InputAck, message);
+        }
+    }
+}
+
+pub fn receive_snapshot_acks(
+    mut server: ResMut<RenetServer>,
+    mut history: ResMut<SnapshotHistory>,
+) {
+    for client_id in server.clients_id().into_iter() {
+        while let Some(message) = server.receive_message(client_id, ClientChannel::SnapshotAck) {
+            let Ok(ack) = bincode::deserialize::<SnapshotAck>(&message) else {
+                continue;
+            };
+            let Some(snapshots) = history.clients.get_mut(&client_id) else {
+                continue;
+            };
+            if ack.tick > snapshots.acked {
+                snapshots.acked = ack.tick;
+                snapshots.sent.retain(|(tick, _)| *tick >= ack.tick);
+            }
         }
     }
 }
@@ -1,7 +1,30 @@
-use bevy::{prelude::{Resource}, utils::HashMap};
-use lib::components::Client;
+use std::collections::VecDeque;
+
+use bevy::{prelude::Resource, utils::HashMap};
+use lib::{components::Client, replication::SnapshotState};
 
 #[derive(Resource, Default)]
 pub struct ServerLobby {
     pub clients: HashMap<u64, Client>,
+    /// The client each connected account plays as.
+    pub accounts: HashMap<u64, u64>,
+}
+
+/// The snapshots sent to one client that it may still ack, oldest first.
+#[derive(Default)]
+pub struct ClientSnapshots {
+    pub acked: u64,
+    pub sent: VecDeque<(u64, SnapshotState)>,
+}
+
+impl ClientSnapshots {
+    /// The state the next snapshot is a delta of, None means from scratch.
+    pub fn baseline(&self) -> Option<&(u64, SnapshotState)> {
+        self.sent.iter().find(|(tick, _)| *tick == self.acked)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SnapshotHistory {
+    pub clients: HashMap<u64, ClientSnapshots>,
 }
@@ -0,0 +1,155 @@
+//! Mob spawners keep their area populated. Each spawner rolls what to
+//! spawn from its table and waits `respawn_ticks` after a death before
+//! topping up again. They're part of the map, `server/data/map.ron`.
+
+use bevy::{prelude::*, utils::HashMap};
+use lib::{
+    abilities::CoolDowns,
+    archetypes::{ArchetypeId, Archetypes},
+    components::{EntityType, Health, SpawnEvent, Target, Tile},
+    nav::NavGrid,
+    resources::Tick,
+    status::StatusEffects,
+};
+use rand::{
+    seq::{IteratorRandom, SliceRandom},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{mob_state_machine, MobAi, MobRange},
+    threat::ThreatTable,
+    world::Map,
+};
+
+/// One row of a spawn table, rows are picked by `weight`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpawnEntry {
+    /// The name of the archetype in `lib/data/archetypes.ron`.
+    pub archetype: String,
+    pub weight: u32,
+    pub min_level: u16,
+    pub max_level: u16,
+}
+
+/// Keeps up to `max_alive` mobs rolled from `table` on walkable tiles of
+/// `area`, which the mobs also stay in.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct MobSpawner {
+    pub table: Vec<SpawnEntry>,
+    pub max_alive: usize,
+    pub respawn_ticks: u64,
+    pub area: MobRange,
+    /// When the missing mobs spawn, None while all are alive.
+    #[serde(skip)]
+    pub respawn_at: Option<u64>,
+}
+
+/// The spawner a mob came from.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct SpawnedBy(pub Entity);
+
+/// Places the spawners, they fill up on the first tick.
+pub fn place_spawners(mut commands: Commands, map: Res<Map>) {
+    for spawner in map.spawners.iter() {
+        commands.spawn(MobSpawner {
+            respawn_at: Some(0),
+            ..spawner.clone()
+        });
+    }
+}
+
+/// Starts the respawn delay of spawners that lost a mob and spawns the
+/// missing ones once it's over.
+pub fn spawn_mobs(
+    mut commands: Commands,
+    mut spawners: Query<(Entity, &mut MobSpawner)>,
+    spawned: Query<&SpawnedBy>,
+    grid: Res<NavGrid>,
+    tick: Res<Tick>,
+    archetypes: Res<Archetypes>,
+    mut spawn_event: EventWriter<SpawnEvent>,
+) {
+    let mut alive: HashMap<Entity, usize> = HashMap::default();
+    for spawned_by in spawned.iter() {
+        *alive.entry(spawned_by.0).or_default() += 1;
+    }
+    let mut rng = rand::thread_rng();
+    for (entity, mut spawner) in spawners.iter_mut() {
+        let alive = alive.get(&entity).copied().unwrap_or_default();
+        if alive >= spawner.max_alive {
+            spawner.respawn_at = None;
+            continue;
+        }
+        let respawn_ticks = spawner.respawn_ticks;
+        if tick.tick < *spawner.respawn_at.get_or_insert(tick.tick + respawn_ticks) {
+            continue;
+        }
+        spawner.respawn_at = None;
+        let (top_left, bottom_right) = (spawner.area.top_left.cell, spawner.area.bottom_right.cell);
+        for _ in alive..spawner.max_alive {
+            let (Ok(entry), Some(tile)) = (
+                spawner
+                    .table
+                    .choose_weighted(&mut rng, |entry| entry.weight),
+                (top_left.0..=bottom_right.0)
+                    .flat_map(|x| {
+                        (top_left.2..=bottom_right.2).map(move |z| Tile::new((x, top_left.1, z)))
+                    })
+                    .filter(|tile| grid.is_walkable(tile))
+                    .choose(&mut rng),
+            ) else {
+                break;
+            };
+            let Some(id) = archetypes.id(&entry.archetype) else {
+                warn!(
+                    "spawner table names unknown archetype {:?}",
+                    entry.archetype
+                );
+                break;
+            };
+            let level = rng.gen_range(entry.min_level..=entry.max_level.max(entry.min_level));
+            let mob = spawn_archetype(&mut commands, &archetypes, id, level, tile);
+            commands
+                .entity(mob)
+                .insert((SpawnedBy(entity), spawner.area.clone()));
+            spawn_event.send(SpawnEvent::new(mob, EntityType::Archetype(id), tile));
+        }
+    }
+}
+
+/// Spawns archetype `id` at `level` with the components its definition
+/// asks for: stats make it fightable, an ai profile makes it a mob.
+pub fn spawn_archetype(
+    commands: &mut Commands,
+    archetypes: &Archetypes,
+    id: ArchetypeId,
+    level: u16,
+    tile: Tile,
+) -> Entity {
+    let entity = commands.spawn((EntityType::Archetype(id), tile)).id();
+    let Some(archetype) = archetypes.get(id) else {
+        return entity;
+    };
+    if let Some(stats) = archetype.stats(level) {
+        commands.entity(entity).insert((
+            Health::new(stats.max_hp),
+            stats,
+            StatusEffects::default(),
+        ));
+    }
+    if let Some(ai) = archetype.ai {
+        commands.entity(entity).insert((
+            Target(None),
+            ThreatTable::default(),
+            CoolDowns::default(),
+            MobAi::new(tile, ai.aggro_radius, ai.step_ticks),
+            mob_state_machine(),
+        ));
+    }
+    if let Some(left_click) = archetype.click.left_click(entity) {
+        commands.entity(entity).insert(left_click);
+    }
+    entity
+}
@@ -38,7 +38,7 @@ impl Trigger for Moving {
                 Err(1.0)
             }
         } else {
-           //println!("Walk");
+            //println!("Walk");
             Err(1.0)
         }
     }
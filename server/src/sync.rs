@@ -1,17 +1,32 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_renet::renet::RenetServer;
 use lib::{
-    channels::ServerChannel,
+    channels::{ServerChannel, SNAPSHOT_BUDGET},
+    combat::{DamageEvent, DeathEvent},
     components::{
-        Client, CombatState, ComponentType, Dummy, EntityType, Health, Player, Scope, SpawnEvent,
-        SyncEvent, Target, Tile, Untraversable, UpdateEvent, OpenState,
+        Client, ComponentType, EntityType, LifecycleEvent, LifecycleMessage, Player, Scope, Tile,
     },
+    net_id::{NetId, NetIds, NetMapped},
+    replication::{ReplicationState, Snapshot, SnapshotState},
+    resources::Tick,
     OpenEvent, ServerEvents,
 };
 
-use crate::events::ChunkRequest;
+use crate::{
+    interest::{observed_chunks, Interest, SpatialIndex},
+    resources::SnapshotHistory,
+};
+
+/// Lifecycle events per message, keeps a message well below the reliable
+/// channel's max_message_size.
+const LIFECYCLE_BATCH: usize = 64;
+
+/// How many unacked snapshots are kept per client, a client that falls
+/// further behind gets a full snapshot again.
+const SNAPSHOT_HISTORY: usize = 32;
 
 /// the server event needs to have a entity field for scoped checking
+/// and implement NetMapped, the entities go out as NetIds
 /// add macro,
 /// add event to server.App
 /// add event to Client.App
@@ -23,13 +38,20 @@ macro_rules! new_server_event {
             mut events: EventReader<$type_name>,
             mut server: ResMut<RenetServer>,
             clients: Query<&Client>,
+            ids: Res<NetIds>,
         ) {
             for event in events.iter() {
+                let Some(net) = event.to_net(&ids) else {
+                    continue;
+                };
+                let message = bincode::serialize(&vec![ServerEvents::$type_name(net)]).unwrap();
                 for client in clients.iter() {
                     if client.scoped_entities.contains(&event.entity) {
-                        let message =
-                            bincode::serialize(&[ServerEvents::$type_name(*event)]).unwrap();
-                        server.send_message(client.id, ServerChannel::ServerEvents, message);
+                        server.send_message(
+                            client.id,
+                            ServerChannel::ServerEvents,
+                            message.clone(),
+                        );
                     }
                 }
             }
@@ -38,121 +60,169 @@ macro_rules! new_server_event {
 }
 
 new_server_event!(send_open_event, OpenEvent);
-macro_rules! update_component {
-    ($fn_name:ident, $type_name:ident) => {
-        pub fn $fn_name(
-            clients: Query<&Client>,
-            components: Query<(Entity, &$type_name), Changed<$type_name>>,
-            mut update_event: EventWriter<SyncEvent>,
-        ) {
-            for client in clients.iter() {
-                for (entity, component) in components.iter() {
-                    if client.scoped_entities.contains(&entity) {
-                        let event = UpdateEvent {
-                            entity,
-                            component: ComponentType::$type_name(*component),
-                        };
-                        update_event.send(SyncEvent::Update(client.id, event));
-                    }
+new_server_event!(send_damage_event, DamageEvent);
+new_server_event!(send_death_event, DeathEvent);
+
+/// Keeps every client's scoped entities in line with the chunks it sees.
+/// Only the chunks entered or left when the player crosses a chunk boundary
+/// and the entities in SpatialIndex.moved are looked at. The spawns and
+/// despawns of a tick go out in order on the lifecycle channel.
+#[allow(clippy::too_many_arguments)]
+pub fn entered_left_scope(
+    mut clients: Query<(&mut Client, &mut Interest)>,
+    entities: Query<(&Tile, &EntityType)>,
+    players: Query<&Tile, (Changed<Tile>, With<Player>)>,
+    alive: Query<()>,
+    mut index: ResMut<SpatialIndex>,
+    mut ids: ResMut<NetIds>,
+    tick: Res<Tick>,
+    mut server: ResMut<RenetServer>,
+) {
+    let moved = std::mem::take(&mut index.moved);
+    for (mut client, mut interest) in clients.iter_mut() {
+        let mut left = vec![];
+        let mut entered = vec![];
+        if let Ok(tile) = players.get(client.controlled_entity) {
+            client.scope = Scope::get(*tile);
+            let chunks = observed_chunks(&client.scope);
+            if chunks != interest.chunks {
+                for chunk in interest.chunks.difference(&chunks) {
+                    left.extend(index.entities(chunk).copied());
+                }
+                for chunk in chunks.difference(&interest.chunks) {
+                    entered.extend(index.entities(chunk).copied());
                 }
+                interest.chunks = chunks;
+            }
+        }
+        for entity in moved.iter() {
+            match index.chunk_of(*entity) {
+                Some(chunk) if interest.chunks.contains(&chunk) => entered.push(*entity),
+                _ => left.push(*entity),
             }
         }
-    };
-}
-//dont forget to add system to App
-//add to component type enum
-//add macro call
-//add system
-//add match arm on client
-update_component!(update_health, Health);
-update_component!(update_tile, Tile);
-update_component!(update_target, Target);
-update_component!(update_combat_state, CombatState);
-update_component!(update_open_state, OpenState);
 
-pub fn send_chunk(
-    query: Query<(Entity, &EntityType, &Tile)>,
-    mut requests: ResMut<Events<ChunkRequest>>,
-    clients: Query<&Client>,
-    mut server: ResMut<RenetServer>,
-) {
-    for request in requests.drain() {
-        for client in clients.iter() {
-            //println!("send load message");
-            if client.id == request.0 {
-                let scope: Vec<(Entity, EntityType, Tile)> = query
-                    .iter()
-                    .filter(|(_entity, _entity_type, pos)| client.scope.check(pos))
-                    .map(|(entity, entity_type, pos)| (entity, *entity_type, *pos))
-                    .collect();
-                let message = bincode::serialize(&scope).unwrap();
-                server.send_message(client.id, ServerChannel::Load, message);
+        let mut events = vec![];
+        for entity in left {
+            if client.scoped_entities.remove(&entity) {
+                if let Some(id) = ids.net_id(entity) {
+                    events.push(LifecycleEvent::Despawn(id));
+                }
             }
         }
+        for entity in entered {
+            if client.scoped_entities.contains(&entity) {
+                continue;
+            }
+            let (Some(id), Ok((tile, entity_type))) = (ids.net_id(entity), entities.get(entity))
+            else {
+                continue;
+            };
+            client.scoped_entities.insert(entity);
+            events.push(LifecycleEvent::Spawn(id, *entity_type, *tile));
+        }
+        for events in events.chunks(LIFECYCLE_BATCH) {
+            let message = LifecycleMessage {
+                tick: tick.tick,
+                events: events.to_vec(),
+            };
+            let message = bincode::serialize(&message).unwrap();
+            server.send_message(client.id, ServerChannel::Lifecycle, message);
+        }
+    }
+    // every client has been sent the despawn, the id isn't needed anymore
+    for entity in moved {
+        if !alive.contains(entity) {
+            ids.remove_entity(entity);
+        }
     }
 }
 
-pub fn create_scope(
-    mut clients: Query<&mut Client, Added<Client>>,
-    entities: Query<(Entity, &Tile)>,
-) {
-    for mut client in clients.iter_mut() {
-        for (e, t) in entities.iter() {
-            if client.scope.check(t) && !client.scoped_entities.contains(&e) {
-                client.scoped_entities.insert(e);
-                //println!("added e into client: {:?}", client.id);
-            }
+/// Gives every entity clients can see a NetId before it is first sent.
+pub fn assign_net_ids(mut ids: ResMut<NetIds>, new: Query<Entity, Added<EntityType>>) {
+    for entity in new.iter() {
+        if ids.net_id(entity).is_none() {
+            ids.allocate(entity);
         }
     }
 }
-/// creat a copy off all the update_component macros that don't check Added<_>
-/// and send update messages if in scope
-/// create  a list of entities from SpawnEvent then use that to sync everything??
-/// then add a second function to the update macro's that read a SyncEvent or whatever
-pub fn entered_left_scope(
-    mut clients: Query<&mut Client>,
-    entities: Query<(Entity, &Tile, &EntityType)>,
+
+/// Rough wire size of one change besides its data: net id, id and length.
+const CHANGE_OVERHEAD: usize = 18;
+
+/// Wire size of a snapshot without changes: both ticks and both lengths.
+const SNAPSHOT_OVERHEAD: usize = 32;
+
+/// Sends every client one snapshot per tick with the replicated components
+/// of its scoped entities, as a delta of the last snapshot it acked.
+/// Changes that don't fit in the budget go out with the next snapshots.
+pub fn send_snapshots(
+    clients: Query<&Client>,
+    state: Res<ReplicationState>,
+    ids: Res<NetIds>,
+    mut history: ResMut<SnapshotHistory>,
+    tick: Res<Tick>,
     mut server: ResMut<RenetServer>,
-    players: Query<(Entity, &Tile), (Changed<Tile>, With<Player>)>,
 ) {
-    for mut client in clients.iter_mut() {
-        for (e, t) in players.iter() {
-            if client.controlled_entity == e {
-                client.scope = Scope::get(*t);
-                //println!("updated scope");
-            }
+    let connected = server.clients_id();
+    history.clients.retain(|id, _| connected.contains(id));
+    for client in clients.iter() {
+        let snapshots = history.clients.entry(client.id).or_default();
+        let (baseline, mut sent) = match snapshots.baseline() {
+            Some((tick, state)) => (*tick, state.clone()),
+            None => (0, SnapshotState::default()),
+        };
+        let scoped: HashMap<NetId, Entity> = client
+            .scoped_entities
+            .iter()
+            .filter_map(|entity| Some((ids.net_id(*entity)?, *entity)))
+            .collect();
+        let removed: Vec<NetId> = sent
+            .keys()
+            .filter(|net_id| !scoped.contains_key(*net_id))
+            .copied()
+            .collect();
+        for net_id in removed.iter() {
+            sent.remove(net_id);
         }
-        for (entity, tile, entity_type) in entities.iter() {
-            if client.scoped_entities.contains(&entity) {
-                if !client.scope.check(tile) {
-                    client.scoped_entities.remove(&entity);
-                    //DESPAWN
-                    let message = bincode::serialize(&entity).unwrap();
-                    server.send_message(client.id, ServerChannel::Despawn, message)
+        let mut changes = vec![];
+        let mut size = SNAPSHOT_OVERHEAD + removed.len() * 8;
+        'entities: for (net_id, entity) in scoped.iter() {
+            let Some(components) = state.entities.get(entity) else {
+                continue;
+            };
+            for (id, data) in components.iter() {
+                if sent.get(net_id).and_then(|sent| sent.get(id)) == Some(data) {
+                    continue;
                 }
-            } else if client.scope.check(tile) {
-                //println!("scope spawn");
-                client.scoped_entities.insert(entity);
-                let message = SpawnEvent {
-                    entity,
-                    entity_type: *entity_type,
-                    tile: *tile,
-                };
-                let message = bincode::serialize(&message).unwrap();
-                server.send_message(client.id, ServerChannel::Spawn, message);
+                size += data.len() + CHANGE_OVERHEAD;
+                if size > SNAPSHOT_BUDGET as usize {
+                    break 'entities;
+                }
+                changes.push((
+                    *net_id,
+                    ComponentType {
+                        id: *id,
+                        data: data.clone(),
+                    },
+                ));
+                sent.entry(*net_id).or_default().insert(*id, data.clone());
             }
         }
-    }
-}
-
-pub fn send_updates(mut update_event: EventReader<SyncEvent>, mut server: ResMut<RenetServer>) {
-    for sync_event in update_event.iter() {
-        match sync_event {
-            SyncEvent::Update(client_id, event) => {
-                let message = bincode::serialize(&event).unwrap();
-                server.send_message(*client_id, ServerChannel::Update, message);
-            }
-            _ => (),
+        if changes.is_empty() && removed.is_empty() {
+            continue;
+        }
+        let snapshot = Snapshot {
+            tick: tick.tick,
+            baseline,
+            changes,
+            removed,
+        };
+        let message = bincode::serialize(&snapshot).unwrap();
+        server.send_message(client.id, ServerChannel::Update, message);
+        snapshots.sent.push_back((tick.tick, sent));
+        if snapshots.sent.len() > SNAPSHOT_HISTORY {
+            snapshots.sent.pop_front();
         }
     }
 }
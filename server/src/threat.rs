@@ -0,0 +1,136 @@
+//! Who mobs fight. Every mob keeps a threat table of the players that hit,
+//! taunted or came close to it and goes after the one with the most.
+
+use bevy::{prelude::*, utils::HashMap};
+use lib::{
+    combat::DamageEvent,
+    components::{Client, Health, Target, Tile},
+    resources::Tick,
+};
+
+use crate::{
+    ai::{Leash, MobRange, Return},
+    resources::ServerLobby,
+};
+
+/// Ticks between taking a tenth off everyone's threat.
+pub const THREAT_DECAY_TICKS: u64 = 10;
+
+/// Threat per attacker, the mob targets the top of it.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ThreatTable {
+    pub threat: HashMap<Entity, u32>,
+}
+
+impl ThreatTable {
+    pub fn add(&mut self, entity: Entity, threat: u32) {
+        *self.threat.entry(entity).or_default() += threat;
+    }
+
+    /// Keeps `entity` on the table with at least `threat`.
+    pub fn keep(&mut self, entity: Entity, threat: u32) {
+        let current = self.threat.entry(entity).or_default();
+        *current = (*current).max(threat);
+    }
+
+    /// Puts `entity` far enough above the top that the mob switches to it.
+    pub fn taunt(&mut self, entity: Entity, threat: u32) {
+        let top = self.top().map_or(0, |(_, top)| top);
+        self.threat.insert(entity, top + top / 10 + threat);
+    }
+
+    pub fn top(&self) -> Option<(Entity, u32)> {
+        self.threat
+            .iter()
+            .map(|(entity, threat)| (*entity, *threat))
+            .max_by_key(|(entity, threat)| (*threat, *entity))
+    }
+
+    /// Who to go after. The `current` target is kept unless someone else
+    /// has over a tenth more threat, so close tables don't flip every hit.
+    pub fn pick(&self, current: Option<Entity>) -> Option<Entity> {
+        let (top, top_threat) = self.top()?;
+        match current.and_then(|current| self.threat.get(&current)) {
+            Some(threat) if top_threat * 10 <= threat * 11 => current,
+            _ => Some(top),
+        }
+    }
+
+    /// Takes a tenth off everyone's threat, at least one.
+    pub fn decay(&mut self) {
+        for threat in self.threat.values_mut() {
+            *threat -= (*threat / 10).max(1);
+        }
+        self.threat.retain(|_, threat| *threat > 0);
+    }
+}
+
+pub fn decay_threat(mut tables: Query<&mut ThreatTable>, tick: Res<Tick>) {
+    if tick.tick % THREAT_DECAY_TICKS != 0 {
+        return;
+    }
+    for mut table in tables.iter_mut() {
+        if !table.threat.is_empty() {
+            table.decay();
+        }
+    }
+}
+
+/// Damage is threat, a miss still counts for one.
+pub fn damage_threat(
+    mut tables: Query<&mut ThreatTable>,
+    mut damage_event: EventReader<DamageEvent>,
+) {
+    for event in damage_event.iter() {
+        if event.attacker == event.entity {
+            continue;
+        }
+        if let Ok(mut table) = tables.get_mut(event.entity) {
+            table.add(
+                event.attacker,
+                event.damage.unwrap_or_default().max(1) as u32,
+            );
+        }
+    }
+}
+
+/// Forgets players that died, disconnected, left the MobRange or can no
+/// longer see the mob. Disconnects go by the lobby, the player entity is
+/// only despawned after this tick.
+pub fn drop_threat(
+    mut mobs: Query<(Entity, &MobRange, &mut ThreatTable)>,
+    players: Query<(&Tile, &Health)>,
+    clients: Query<&Client>,
+    lobby: Res<ServerLobby>,
+) {
+    let scopes: HashMap<Entity, &Client> = clients
+        .iter()
+        .filter(|client| lobby.clients.contains_key(&client.id))
+        .map(|client| (client.controlled_entity, client))
+        .collect();
+    for (mob, range, mut table) in mobs.iter_mut() {
+        table.threat.retain(|player, _| {
+            let alive_in_range = players
+                .get(*player)
+                .is_ok_and(|(tile, health)| health.hp > 0 && range.check(tile));
+            let sees_mob = scopes
+                .get(player)
+                .is_some_and(|client| client.scoped_entities.contains(&mob));
+            alive_in_range && sees_mob
+        });
+    }
+}
+
+/// Targets the top of the threat table, leashing and returning mobs
+/// ignore it.
+#[allow(clippy::type_complexity)]
+pub fn pick_targets(
+    mut mobs: Query<(&ThreatTable, &mut Target), (Without<Leash>, Without<Return>)>,
+) {
+    for (table, mut target) in mobs.iter_mut() {
+        let next = table.pick(target.0);
+        if target.0 != next {
+            target.0 = next;
+        }
+    }
+}
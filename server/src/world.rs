@@ -1,34 +1,226 @@
+//! The map the server loads on startup, `server/data/map.ron`. Floors are
+//! grids of glyphs, one string per x and one glyph per z, the rest of the
+//! level is listed next to them. `--dungeon` generates one instead, see
+//! `dungeon.rs`.
+
+use std::path::Path;
+
 use bevy::prelude::*;
-use lib::components::{EntityType, Instance, Tile, Wall};
-
-pub fn create_tiles(mut commands: Commands) {
-    let instance = commands.spawn(Instance).id();
-    let y: u32 = 0;
-    for x in (0..20).step_by(10) {
-        for z in (0..20).step_by(10) {
-            let tiles = spawn_chunk(&mut commands, (x, y, z));
-            for child in tiles {
-                commands.entity(instance).push_children(&[child]);
+use lib::components::{Arch, Door, EntityType, Instance, Lever, OpenState, Stairs, Tile, Wall};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::spawner::MobSpawner;
+
+/// Read at startup when it exists, so maps change without a rebuild.
+pub const MAP_FILE: &str = "server/data/map.ron";
+
+/// What a glyph of a floor places, everything but empty tiles has floor
+/// under it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Glyph {
+    Empty,
+    Floor,
+    Wall,
+    /// An arch whose pillars are this tile and the one two further along
+    /// z, `door` hangs a closed door in the passage between them.
+    ArchAlongZ {
+        door: bool,
+    },
+    /// The same along x.
+    ArchAlongX {
+        door: bool,
+    },
+    Lever,
+    SpawnPoint,
+}
+
+impl Glyph {
+    pub fn from_char(glyph: char) -> Option<Self> {
+        Some(match glyph {
+            ' ' => Glyph::Empty,
+            '.' => Glyph::Floor,
+            '#' => Glyph::Wall,
+            'D' => Glyph::ArchAlongZ { door: true },
+            'A' => Glyph::ArchAlongZ { door: false },
+            'd' => Glyph::ArchAlongX { door: true },
+            'a' => Glyph::ArchAlongX { door: false },
+            'L' => Glyph::Lever,
+            'S' => Glyph::SpawnPoint,
+            _ => return None,
+        })
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Glyph::Empty => ' ',
+            Glyph::Floor => '.',
+            Glyph::Wall => '#',
+            Glyph::ArchAlongZ { door: true } => 'D',
+            Glyph::ArchAlongZ { door: false } => 'A',
+            Glyph::ArchAlongX { door: true } => 'd',
+            Glyph::ArchAlongX { door: false } => 'a',
+            Glyph::Lever => 'L',
+            Glyph::SpawnPoint => 'S',
+        }
+    }
+}
+
+/// One level of the map at height `y`, `rows[x]` holds the glyphs of z.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Floor {
+    pub y: u32,
+    pub rows: Vec<String>,
+}
+
+impl Floor {
+    /// Every glyph with its tile, unknown glyphs are skipped.
+    pub fn glyphs(&self) -> impl Iterator<Item = (Tile, Glyph)> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(x, row)| {
+            row.chars().enumerate().filter_map(move |(z, glyph)| {
+                let tile = Tile::new((x as u32, self.y, z as u32));
+                Some((tile, Glyph::from_char(glyph)?))
+            })
+        })
+    }
+}
+
+/// `count` of `item` lying on `tile`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlacedItem {
+    pub item: String,
+    pub count: u16,
+    pub tile: Tile,
+}
+
+/// Stairs on `tile` that take players to `to`, usually on another floor.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlacedStairs {
+    pub tile: Tile,
+    pub to: Tile,
+}
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Map {
+    pub floors: Vec<Floor>,
+    #[serde(default)]
+    pub spawners: Vec<MobSpawner>,
+    #[serde(default)]
+    pub items: Vec<PlacedItem>,
+    #[serde(default)]
+    pub stairs: Vec<PlacedStairs>,
+}
+
+impl Map {
+    pub fn from_ron(ron: &str) -> Result<Self, String> {
+        let map: Self = ron::from_str(ron).map_err(|err| format!("parsing map: {err}"))?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let ron = std::fs::read_to_string(path)
+            .map_err(|err| format!("reading {}: {err}", path.display()))?;
+        Self::from_ron(&ron)
+    }
+
+    /// MAP_FILE if it's there, the copy built into the binary if not.
+    pub fn load() -> Result<Self, String> {
+        if Path::new(MAP_FILE).exists() {
+            return Self::from_file(MAP_FILE);
+        }
+        Self::from_ron(include_str!("../data/map.ron"))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for floor in self.floors.iter() {
+            for (x, row) in floor.rows.iter().enumerate() {
+                if let Some(glyph) = row.chars().find(|c| Glyph::from_char(*c).is_none()) {
+                    return Err(format!(
+                        "unknown glyph {glyph:?} on floor {} row {x}",
+                        floor.y
+                    ));
+                }
             }
         }
+        // arriving on stairs would take them right away
+        if let Some(stairs) = self
+            .stairs
+            .iter()
+            .find(|stairs| self.stairs.iter().any(|other| other.tile == stairs.to))
+        {
+            return Err(format!("stairs on {:?} lead onto stairs", stairs.tile));
+        }
+        if self.spawn_points().is_empty() {
+            return Err("the map has no spawn point".to_string());
+        }
+        Ok(())
     }
-    //for z in 4..10 {
-        //commands.spawn((EntityType::Wall(Wall::Vertical), Tile::new((4, 0, z))));
-    //}
-}
-
-pub fn spawn_chunk(commands: &mut Commands, start: (u32, u32, u32)) -> Vec<Entity> {
-    let y: u32 = 0;
-    let end = (start.0 + 10, start.1, start.2 + 10);
-    let mut tiles = vec![];
-    for x in start.0..end.0 {
-        for z in start.2..end.2 {
-            tiles.push(
-                commands
-                    .spawn((EntityType::Tile, Tile { cell: (x, y, z) }))
-                    .id(),
-            );
+
+    pub fn spawn_points(&self) -> Vec<Tile> {
+        self.floors
+            .iter()
+            .flat_map(Floor::glyphs)
+            .filter(|(_, glyph)| *glyph == Glyph::SpawnPoint)
+            .map(|(tile, _)| tile)
+            .collect()
+    }
+}
+
+/// Where players join and respawn, one of them at random.
+#[derive(Resource, Clone, Debug)]
+pub struct SpawnPoints(pub Vec<Tile>);
+
+impl SpawnPoints {
+    pub fn pick(&self) -> Tile {
+        self.0
+            .choose(&mut rand::thread_rng())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Spawns the floors of the map, their tiles are children of an Instance
+/// per floor.
+pub fn load_map(mut commands: Commands, map: Res<Map>) {
+    commands.insert_resource(SpawnPoints(map.spawn_points()));
+    for floor in map.floors.iter() {
+        let instance = commands.spawn(Instance).id();
+        for (tile, glyph) in floor.glyphs() {
+            if glyph == Glyph::Empty {
+                continue;
+            }
+            let floor_tile = commands.spawn((EntityType::Tile, tile)).id();
+            commands.entity(instance).add_child(floor_tile);
+            match glyph {
+                Glyph::Wall => {
+                    commands.spawn((EntityType::Wall(Wall::Horizontal), tile));
+                }
+                Glyph::ArchAlongZ { door } => {
+                    commands.spawn((EntityType::Arch(Arch::Horizontal), tile));
+                    if door {
+                        commands.spawn((
+                            EntityType::Door(Door::Horizontal),
+                            tile,
+                            OpenState::Closed,
+                        ));
+                    }
+                }
+                Glyph::ArchAlongX { door } => {
+                    commands.spawn((EntityType::Arch(Arch::Vertical), tile));
+                    if door {
+                        commands.spawn((EntityType::Door(Door::Vertical), tile, OpenState::Closed));
+                    }
+                }
+                Glyph::Lever => {
+                    commands.spawn((EntityType::Lever(Lever), tile));
+                }
+                Glyph::Empty | Glyph::Floor | Glyph::SpawnPoint => (),
+            }
         }
     }
-    tiles
+    for stairs in map.stairs.iter() {
+        commands.spawn((EntityType::Stairs(Stairs { to: stairs.to }), stairs.tile));
+    }
 }
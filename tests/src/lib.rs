@@ -0,0 +1,228 @@
+//! Runs a server and simulated clients in one process without rendering.
+//! They talk over loopback udp and share a clock that only moves when the
+//! harness steps, so every step is exactly one server tick.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, UdpSocket},
+    time::Instant,
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_renet::renet::{RenetClient, RenetServer};
+use client::{connection::renet_client, plugins::ClientNetPlugin};
+use lib::{
+    archetypes::Archetypes,
+    components::{EntityType, Player, PlayerCommand, Tile},
+    config::NetConfig,
+    net_id::{NetId, NetIds},
+};
+use server::{connection::renet_server, world::Map, ServerPlugin};
+
+/// Steps it may take the renet handshake to finish.
+const CONNECT_TICKS: u32 = 50;
+
+pub struct Harness {
+    pub server: App,
+    pub clients: Vec<App>,
+    pub config: NetConfig,
+    now: Instant,
+}
+
+impl Harness {
+    /// A server and `clients` connected clients, ready to step.
+    pub fn new(clients: usize) -> Self {
+        Self::with_map(clients, Map::load().unwrap())
+    }
+
+    /// The same on `map` instead of the server's map file.
+    pub fn with_map(clients: usize, map: Map) -> Self {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let config = NetConfig {
+            bind_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            public_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: socket.local_addr().unwrap().port(),
+            ..Default::default()
+        };
+        let mut server = App::new();
+        server.add_plugins(MinimalPlugins);
+        server.add_plugin(ServerPlugin);
+        server.insert_resource(map);
+        server.insert_resource(FixedTime::new(config.tick()));
+        server.insert_resource(renet_server(&config, socket).unwrap());
+        let mut harness = Self {
+            server,
+            clients: vec![],
+            config,
+            now: Instant::now(),
+        };
+        for _ in 0..clients {
+            harness.add_client();
+        }
+        harness.step();
+        harness.connect();
+        harness
+    }
+
+    /// Adds a headless client, it connects on the following steps.
+    pub fn add_client(&mut self) -> usize {
+        let index = self.clients.len();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugin(ClientNetPlugin);
+        app.insert_resource(renet_client(&self.config, index as u64 + 1).unwrap());
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+        self.clients.push(app);
+        index
+    }
+
+    /// Advances the shared clock by one tick, updates the server once,
+    /// which runs exactly one fixed tick, then every client.
+    pub fn step(&mut self) {
+        self.now += self.config.tick();
+        self.server
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+        self.server.update();
+        for client in self.clients.iter_mut() {
+            client.insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+            client.update();
+        }
+    }
+
+    /// Runs one tick on the server only, the clients get what it sent on
+    /// their next step as if the packets had been held up.
+    pub fn step_server(&mut self) {
+        self.now += self.config.tick();
+        self.server
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+        self.server.update();
+    }
+
+    pub fn run(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Steps until `done` holds, at most `ticks` times. True if it did.
+    pub fn run_until(&mut self, ticks: u32, mut done: impl FnMut(&mut Self) -> bool) -> bool {
+        for _ in 0..ticks {
+            if done(self) {
+                return true;
+            }
+            self.step();
+        }
+        done(self)
+    }
+
+    /// Steps until every client is connected.
+    pub fn connect(&mut self) {
+        let connected = self.run_until(CONNECT_TICKS, |harness| {
+            let clients = harness.clients.len();
+            harness
+                .server
+                .world
+                .resource::<RenetServer>()
+                .clients_id()
+                .len()
+                == clients
+                && (0..clients).all(|client| harness.player(client).is_some())
+        });
+        assert!(connected, "clients didn't connect in {CONNECT_TICKS} ticks");
+    }
+
+    pub fn client_id(&self, client: usize) -> u64 {
+        self.clients[client]
+            .world
+            .resource::<RenetClient>()
+            .client_id()
+    }
+
+    /// The server entity of `client`'s player.
+    pub fn player(&mut self, client: usize) -> Option<Entity> {
+        let id = self.client_id(client);
+        let mut players = self.server.world.query::<(Entity, &Player)>();
+        players
+            .iter(&self.server.world)
+            .find(|(_, player)| player.id == id)
+            .map(|(entity, _)| entity)
+    }
+
+    /// Entities of `entity_type` on the server.
+    pub fn server_entities(&mut self, entity_type: EntityType) -> Vec<Entity> {
+        let mut entities = self.server.world.query::<(Entity, &EntityType)>();
+        entities
+            .iter(&self.server.world)
+            .filter(|(_, other)| **other == entity_type)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    /// The EntityType of the archetype called `name`.
+    pub fn archetype(&self, name: &str) -> EntityType {
+        let id = self.server.world.resource::<Archetypes>().id(name);
+        EntityType::Archetype(id.unwrap_or_else(|| panic!("no archetype {name:?}")))
+    }
+
+    /// Where the server has `entity`.
+    pub fn server_tile(&self, entity: Entity) -> Option<Tile> {
+        self.server.world.get::<Tile>(entity).copied()
+    }
+
+    /// Where `client` has the server's `entity`, None if it isn't
+    /// replicated to it.
+    pub fn client_tile(&self, client: usize, entity: Entity) -> Option<Tile> {
+        let id = self.server.world.resource::<NetIds>().net_id(entity)?;
+        self.client_tile_of(client, id)
+    }
+
+    fn client_tile_of(&self, client: usize, id: NetId) -> Option<Tile> {
+        let world = &self.clients[client].world;
+        let entity = world.resource::<NetIds>().entity(id)?;
+        world.get_entity(entity)?.get::<Tile>().copied()
+    }
+
+    /// `client`'s copy of the `C` of the server's `entity`.
+    pub fn client_component<C: Component>(&self, client: usize, entity: Entity) -> Option<&C> {
+        let id = self.net_id(entity)?;
+        let world = &self.clients[client].world;
+        let entity = world.resource::<NetIds>().entity(id)?;
+        world.get_entity(entity)?.get::<C>()
+    }
+
+    /// Queues a command as if `client`'s player had clicked or pressed a key.
+    pub fn send(&mut self, client: usize, command: PlayerCommand) {
+        self.clients[client].world.send_event(command);
+    }
+
+    pub fn disconnect(&mut self, client: usize) {
+        self.clients[client]
+            .world
+            .resource_mut::<RenetClient>()
+            .disconnect();
+    }
+
+    /// Panics unless `client` sees `entity` at `tile` within `ticks` ticks.
+    pub fn assert_sees(&mut self, client: usize, entity: Entity, tile: Tile, ticks: u32) {
+        let seen = self.run_until(ticks, |harness| {
+            harness.client_tile(client, entity) == Some(tile)
+        });
+        assert!(
+            seen,
+            "client {client} has {entity:?} at {:?} after {ticks} ticks, expected {tile:?}",
+            self.client_tile(client, entity)
+        );
+    }
+
+    /// Panics unless `client` stops seeing `id` within `ticks` ticks, its
+    /// entity may already be gone on the server.
+    pub fn assert_not_sees(&mut self, client: usize, id: NetId, ticks: u32) {
+        let gone = self.run_until(ticks, |harness| {
+            harness.client_tile_of(client, id).is_none()
+        });
+        assert!(gone, "client {client} still has {id:?} after {ticks} ticks");
+    }
+
+    pub fn net_id(&self, entity: Entity) -> Option<NetId> {
+        self.server.world.resource::<NetIds>().net_id(entity)
+    }
+}
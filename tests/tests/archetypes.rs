@@ -0,0 +1,119 @@
+use bevy::{
+    ecs::system::{CommandQueue, Commands},
+    prelude::Entity,
+};
+use lib::{
+    archetypes::{ArchetypeId, Archetypes},
+    combat::Stats,
+    components::{EntityType, Tile},
+    nav::NavGrid,
+    net_id::NetIds,
+};
+use server::{ai::MobAi, spawner::spawn_archetype};
+use tests::Harness;
+
+/// Spawns archetype `id` on the server like a spawner would.
+fn spawn(harness: &mut Harness, id: ArchetypeId, level: u16, tile: Tile) -> Entity {
+    let world = &mut harness.server.world;
+    let mut queue = CommandQueue::default();
+    let entity = {
+        let mut commands = Commands::new(&mut queue, world);
+        spawn_archetype(
+            &mut commands,
+            world.resource::<Archetypes>(),
+            id,
+            level,
+            tile,
+        )
+    };
+    queue.apply(world);
+    entity
+}
+
+#[test]
+fn blocking_props_block_pathing_for_everyone() {
+    let mut harness = Harness::new(1);
+    let EntityType::Archetype(id) = harness.archetype("crate") else {
+        unreachable!();
+    };
+    let tile = Tile::new((5, 0, 12));
+    assert!(harness
+        .server
+        .world
+        .resource::<NavGrid>()
+        .is_walkable(&tile));
+    let prop = spawn(&mut harness, id, 1, tile);
+    assert!(harness.server.world.get::<Stats>(prop).is_none());
+
+    let blocked = harness.run_until(20, |harness| {
+        !harness
+            .server
+            .world
+            .resource::<NavGrid>()
+            .is_walkable(&tile)
+            && !harness.clients[0]
+                .world
+                .resource::<NavGrid>()
+                .is_walkable(&tile)
+    });
+    assert!(blocked, "the crate didn't block its tile on both sides");
+}
+
+#[test]
+fn new_archetypes_need_no_code() {
+    let mut harness = Harness::new(1);
+    let bats = Archetypes::from_ron(
+        r#"[(
+            name: "cave bat",
+            stats: Some((attack: 2, defense: 1, max_hp: 20)),
+            per_level: (attack: 1, defense: 0, max_hp: 5),
+            click: Attack,
+            ai: Some((aggro_radius: 5, step_ticks: 4)),
+        )]"#,
+    )
+    .unwrap();
+    let id = bats.id("cave bat").unwrap();
+    harness.server.world.insert_resource(bats);
+    harness.clients[0]
+        .world
+        .insert_resource(Archetypes::from_ron(r#"[(name: "cave bat")]"#).unwrap());
+
+    let bat = spawn(&mut harness, id, 3, Tile::new((6, 0, 6)));
+    assert_eq!(
+        harness.server.world.get::<Stats>(bat),
+        Some(&Stats::new(4, 1, 30))
+    );
+    assert_eq!(
+        harness.server.world.get::<MobAi>(bat).unwrap().aggro_radius,
+        5
+    );
+    let seen = harness.run_until(10, |harness| {
+        let Some(net_id) = harness.net_id(bat) else {
+            return false;
+        };
+        let world = &harness.clients[0].world;
+        world
+            .resource::<NetIds>()
+            .entity(net_id)
+            .and_then(|entity| world.get::<EntityType>(entity))
+            == Some(&EntityType::Archetype(id))
+    });
+    assert!(seen, "the client never saw the bat");
+}
+
+#[test]
+fn huge_levels_cap_the_stats() {
+    let giants = Archetypes::from_ron(
+        r#"[(
+            name: "giant",
+            stats: Some((attack: 60000, defense: 1, max_hp: 100)),
+            per_level: (attack: 1000, defense: 0, max_hp: 1000),
+        )]"#,
+    )
+    .unwrap();
+    let giant = giants.get(giants.id("giant").unwrap()).unwrap();
+    assert_eq!(
+        giant.stats(u16::MAX),
+        Some(Stats::new(u16::MAX, 1, u16::MAX))
+    );
+}
@@ -0,0 +1,271 @@
+use bevy::prelude::With;
+use lib::{
+    abilities::{Abilities, AbilityId},
+    combat::{DamageEvent, DeathEvent, Mana, Stats},
+    components::{CombatState, ControlledEntity, Health, PlayerCommand, Target, Tile},
+    nav::is_adjacent,
+    resources::Tick,
+    status::{StatusEffect, StatusEffects, StatusKind},
+};
+use server::{
+    ai::{self, MobAi},
+    combat::CombatEvent,
+    world::Map,
+};
+use tests::Harness;
+
+#[test]
+fn dead_mobs_despawn_for_clients() {
+    let mut harness = Harness::new(1);
+    let slime = harness.server_entities(harness.archetype("slime"))[0];
+    let seen = harness.run_until(10, |harness| harness.client_tile(0, slime).is_some());
+    assert!(seen, "client never saw the slime");
+    let id = harness.net_id(slime).unwrap();
+    let player = harness.player(0).unwrap();
+    harness
+        .server
+        .world
+        .entity_mut(player)
+        .insert(Stats::new(1000, 0, 50));
+    let dead = harness.run_until(50, |harness| {
+        if harness.server.world.get_entity(slime).is_none() {
+            return true;
+        }
+        let attack = CombatEvent::new(player, slime, AbilityId::AUTO_ATTACK);
+        harness.server.world.send_event(attack);
+        false
+    });
+    assert!(dead, "slime survived 50 attacks");
+    harness.assert_not_sees(0, id, 10);
+}
+
+#[test]
+fn auto_attack_walks_into_melee_range() {
+    let mut harness = Harness::new(1);
+    let dummy = harness.server_entities(harness.archetype("training dummy"))[0];
+    let player = harness.player(0).unwrap();
+    harness
+        .server
+        .world
+        .entity_mut(player)
+        .insert(Target(Some(dummy)));
+    let targeted = harness.run_until(10, |harness| {
+        let target = harness.clients[0]
+            .world
+            .query::<&Target>()
+            .iter(&harness.clients[0].world)
+            .any(|target| target.0.is_some());
+        target && harness.client_tile(0, dummy).is_some()
+    });
+    assert!(targeted, "client never saw its target");
+    let dummy_tile = harness.server_tile(dummy).unwrap();
+    assert!(!is_adjacent(
+        &harness.server_tile(player).unwrap(),
+        &dummy_tile
+    ));
+    harness.send(0, PlayerCommand::UseAbility(AbilityId::AUTO_ATTACK, None));
+    let attacked = harness.run_until(30, |harness| {
+        let punching = harness.server.world.get::<CombatState>(player);
+        matches!(punching, Some(CombatState::Punching(_)))
+    });
+    assert!(attacked, "never attacked the dummy");
+    let tile = harness.server_tile(player).unwrap();
+    assert!(is_adjacent(&tile, &dummy_tile));
+    harness.assert_sees(0, player, tile, 10);
+}
+
+#[test]
+fn casts_cost_mana_and_go_off_after_the_cast_time() {
+    let mut harness = Harness::new(1);
+    let player = harness.player(0).unwrap();
+    let abilities = Abilities::default();
+    let (bandage, ability) = abilities
+        .iter()
+        .find(|(_, ability)| ability.name == "bandage")
+        .unwrap();
+    // keep the slimes from hitting the player
+    for slime in harness.server_entities(harness.archetype("slime")) {
+        harness
+            .server
+            .world
+            .get_mut::<MobAi>(slime)
+            .unwrap()
+            .aggro_radius = 0;
+    }
+    harness
+        .server
+        .world
+        .entity_mut(player)
+        .insert(Health::new(10));
+    let mana = harness.server.world.get::<Mana>(player).unwrap().mp;
+    harness.send(0, PlayerCommand::UseAbility(bandage, None));
+    let cast = harness.run_until(10, |harness| {
+        harness.server.world.get::<Mana>(player).unwrap().mp < mana
+    });
+    assert!(cast, "never started casting");
+    assert_eq!(harness.server.world.get::<Health>(player).unwrap().hp, 10);
+    let healed = harness.run_until(ability.cast_ticks as u32 + 2, |harness| {
+        harness.server.world.get::<Health>(player).unwrap().hp >= 25
+    });
+    assert!(healed, "the cast never went off");
+}
+
+#[test]
+fn poison_is_replicated_and_wears_off() {
+    let mut harness = Harness::new(1);
+    let player = harness.player(0).unwrap();
+    let tick = *harness.server.world.resource::<Tick>();
+    harness
+        .server
+        .world
+        .get_mut::<StatusEffects>(player)
+        .unwrap()
+        .apply(StatusKind::Poison, 20, &tick);
+    let replicated = harness.run_until(5, |harness| {
+        let world = &mut harness.clients[0].world;
+        world
+            .query_filtered::<&StatusEffects, With<ControlledEntity>>()
+            .iter(world)
+            .any(|statuses| statuses.stacks(StatusKind::Poison) == 1)
+    });
+    assert!(replicated, "client never saw the poison");
+    harness.run(20);
+    let statuses = harness.server.world.get::<StatusEffects>(player).unwrap();
+    assert!(statuses.effects.is_empty());
+    assert!(harness.server.world.get::<Health>(player).unwrap().hp < 50);
+}
+
+#[test]
+fn large_statuses_saturate() {
+    let mut statuses = StatusEffects {
+        effects: vec![
+            StatusEffect {
+                kind: StatusKind::Weaken,
+                stacks: u16::MAX,
+                until: u64::MAX,
+            },
+            StatusEffect {
+                kind: StatusKind::Slow,
+                stacks: u16::MAX,
+                until: u64::MAX,
+            },
+        ],
+    };
+    // weaken caps at three stacks, three quarters off
+    assert_eq!(statuses.damage(u16::MAX), 16384);
+    assert_eq!(statuses.damage(1), 1);
+    assert_eq!(statuses.cooldown(u64::MAX), u64::MAX);
+    assert_eq!(statuses.move_interval(), Some(1 + u16::MAX as u64));
+    let tick = Tick { tick: u64::MAX };
+    statuses.apply(StatusKind::Slow, 10, &tick);
+    assert_eq!(
+        statuses.stacks(StatusKind::Slow),
+        StatusKind::Slow.max_stacks()
+    );
+    assert_eq!(statuses.effects[1].until, u64::MAX);
+}
+
+#[test]
+fn slimes_aggro_on_players_close_by_and_leash_home() {
+    let mut harness = Harness::new(1);
+    let slime = harness.server_entities(harness.archetype("slime"))[0];
+    let home = harness.server.world.get::<MobAi>(slime).unwrap().home;
+    let player = harness.player(0).unwrap();
+    // next to the spawn point, hitting hard but never killing in one go,
+    // the respawn would heal the player before it is checked
+    harness
+        .server
+        .world
+        .entity_mut(slime)
+        .insert((Tile::new((4, 0, 4)), Stats::new(40, 0, 99)));
+    let hit = harness.run_until(100, |harness| {
+        harness.server.world.get::<Health>(player).unwrap().hp < 50
+    });
+    assert!(hit, "the slime never attacked");
+    harness
+        .server
+        .world
+        .entity_mut(player)
+        .insert(Tile::new((12, 0, 12)));
+    let home_again = harness.run_until(100, |harness| {
+        let world = &harness.server.world;
+        world.get::<Target>(slime).unwrap().0.is_none()
+            && world.get::<ai::Idle>(slime).is_some()
+            && world.get::<Tile>(slime) == Some(&home)
+    });
+    assert!(home_again, "the slime never leashed home");
+}
+
+#[test]
+fn spawners_repopulate_after_the_respawn_delay() {
+    let mut harness = Harness::new(1);
+    let spawner = Map::load()
+        .unwrap()
+        .spawners
+        .into_iter()
+        .find(|spawner| spawner.table.iter().any(|entry| entry.archetype == "slime"))
+        .unwrap();
+    let slimes = harness.server_entities(harness.archetype("slime"));
+    assert_eq!(slimes.len(), spawner.max_alive);
+    harness
+        .server
+        .world
+        .send_event(DeathEvent { entity: slimes[0] });
+    harness.run(2);
+    assert_eq!(
+        harness.server_entities(harness.archetype("slime")).len(),
+        spawner.max_alive - 1
+    );
+    let respawned = harness.run_until(spawner.respawn_ticks as u32 + 5, |harness| {
+        harness.server_entities(harness.archetype("slime")).len() == spawner.max_alive
+    });
+    assert!(respawned, "the spawner never replaced the slime");
+}
+
+#[test]
+fn mobs_go_after_the_top_threat_and_taunts_take_it() {
+    let mut harness = Harness::new(2);
+    let slime = harness.server_entities(harness.archetype("slime"))[0];
+    harness
+        .server
+        .world
+        .get_mut::<MobAi>(slime)
+        .unwrap()
+        .aggro_radius = 0;
+    let (first, second) = (harness.player(0).unwrap(), harness.player(1).unwrap());
+    let taunt = Abilities::default()
+        .iter()
+        .find(|(_, ability)| ability.name == "taunt")
+        .unwrap()
+        .0;
+    let scoped = harness.run_until(10, |harness| {
+        harness.client_tile(0, slime).is_some() && harness.client_tile(1, slime).is_some()
+    });
+    assert!(scoped, "the players never saw the slime");
+    for (attacker, damage) in [(first, 10), (second, 5)] {
+        harness.server.world.send_event(DamageEvent {
+            entity: slime,
+            attacker,
+            damage: Some(damage),
+        });
+    }
+    harness.run(2);
+    assert_eq!(
+        harness.server.world.get::<Target>(slime).unwrap().0,
+        Some(first)
+    );
+    harness
+        .server
+        .world
+        .send_event(CombatEvent::new(second, slime, taunt));
+    harness.run(2);
+    assert_eq!(
+        harness.server.world.get::<Target>(slime).unwrap().0,
+        Some(second)
+    );
+    harness.disconnect(1);
+    let dropped = harness.run_until(30, |harness| {
+        harness.server.world.get::<Target>(slime).unwrap().0 == Some(first)
+    });
+    assert!(dropped, "the slime kept the disconnected player as target");
+}
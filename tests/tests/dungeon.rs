@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+use bevy::utils::HashSet;
+use lib::components::Tile;
+use server::{
+    dungeon::{generate, DungeonConfig},
+    world::{Floor, Map},
+};
+use tests::Harness;
+
+/// Floor tiles of `floor` reachable from `start`, walking through door
+/// passages as if they were open.
+fn reachable(floor: &Floor, start: Tile) -> HashSet<(usize, usize)> {
+    let walkable = |x: usize, z: usize| {
+        floor
+            .rows
+            .get(x)
+            .and_then(|row| row.chars().nth(z))
+            .is_some_and(|glyph| glyph == '.' || glyph == 'S')
+    };
+    let start = (start.cell.0 as usize, start.cell.2 as usize);
+    let mut seen = HashSet::from_iter([start]);
+    let mut open = VecDeque::from([start]);
+    while let Some((x, z)) = open.pop_front() {
+        for (nx, nz) in [
+            (x + 1, z),
+            (x.wrapping_sub(1), z),
+            (x, z + 1),
+            (x, z.wrapping_sub(1)),
+        ] {
+            if walkable(nx, nz) && seen.insert((nx, nz)) {
+                open.push_back((nx, nz));
+            }
+        }
+    }
+    seen
+}
+
+fn floor_tiles(floor: &Floor) -> usize {
+    floor
+        .rows
+        .iter()
+        .flat_map(|row| row.chars())
+        .filter(|glyph| *glyph == '.' || *glyph == 'S')
+        .count()
+}
+
+#[test]
+fn seeds_reproduce_their_dungeon() {
+    let config = DungeonConfig::default();
+    let map = format!("{:?}", generate(7, &config));
+    assert_eq!(map, format!("{:?}", generate(7, &config)));
+    assert_ne!(map, format!("{:?}", generate(8, &config)));
+}
+
+#[test]
+fn every_room_can_be_reached() {
+    let config = DungeonConfig::default();
+    for seed in 0..20 {
+        let map = generate(seed, &config);
+        map.validate().unwrap();
+        assert_eq!(map.floors.len(), config.floors as usize);
+        assert!(!map.spawners.is_empty());
+        for floor in map.floors.iter() {
+            // the spawn point on top, where the stairs down arrive below
+            let start = map
+                .spawn_points()
+                .into_iter()
+                .chain(map.stairs.iter().map(|stairs| stairs.to))
+                .find(|tile| tile.cell.1 == floor.y)
+                .unwrap_or_else(|| panic!("seed {seed} has no way onto floor {}", floor.y));
+            assert_eq!(
+                reachable(floor, start).len(),
+                floor_tiles(floor),
+                "seed {seed} floor {} has rooms cut off",
+                floor.y
+            );
+        }
+    }
+}
+
+#[test]
+fn stairs_take_players_down() {
+    let map: Map = generate(3, &DungeonConfig::default());
+    let stairs = map
+        .stairs
+        .iter()
+        .find(|stairs| stairs.tile.cell.1 == 0)
+        .cloned()
+        .unwrap();
+    let mut harness = Harness::with_map(1, map);
+    let player = harness.player(0).unwrap();
+    harness.server.world.entity_mut(player).insert(stairs.tile);
+    harness.run(2);
+    assert_eq!(harness.server_tile(player), Some(stairs.to));
+    assert_eq!(stairs.to.cell.1, 1);
+}
+
+#[test]
+fn broken_configs_are_rejected() {
+    let ron = include_str!("../../server/data/dungeon.ron");
+    for (field, broken) in [
+        ("size: (40, 40)", "size: (2, 2)"),
+        ("size: (40, 40)", "size: (40, 5)"),
+        ("floors: 3", "floors: 0"),
+        ("loot_chance: 0.5", "loot_chance: 2.0"),
+    ] {
+        assert!(ron.contains(field));
+        let config = DungeonConfig::from_ron(&ron.replace(field, broken));
+        assert!(config.is_err(), "{broken} was accepted");
+    }
+    let smallest = DungeonConfig {
+        size: (5, 5),
+        min_room: 3,
+        max_room: 3,
+        ..DungeonConfig::default()
+    };
+    smallest.validate().unwrap();
+    generate(1, &smallest).validate().unwrap();
+}
@@ -0,0 +1,207 @@
+use bevy::prelude::{Entity, With};
+use lib::{
+    combat::Stats,
+    components::{ControlledEntity, EntityType, LeftClick, PlayerCommand, Tile},
+    equipment::{EquipSlot, Equipment},
+    items::{Inventory, ItemStack, Items},
+    net_id::NetIds,
+};
+use server::InteractEvent;
+use tests::Harness;
+
+/// The inventory `client` has for its own player.
+fn client_inventory(harness: &mut Harness, client: usize) -> Option<Inventory> {
+    let world = &mut harness.clients[client].world;
+    world
+        .query_filtered::<&Inventory, With<ControlledEntity>>()
+        .iter(world)
+        .next()
+        .cloned()
+}
+
+/// `client`'s entity for the server's `entity`.
+fn client_entity(harness: &Harness, client: usize, entity: Entity) -> Option<Entity> {
+    let id = harness.net_id(entity)?;
+    harness.clients[client]
+        .world
+        .resource::<NetIds>()
+        .entity(id)
+}
+
+#[test]
+fn picking_up_fills_only_the_owners_inventory() {
+    let mut harness = Harness::new(2);
+    let potion = harness
+        .server
+        .world
+        .resource::<Items>()
+        .id("health potion")
+        .unwrap();
+    let item = harness.server_entities(EntityType::Item(potion))[0];
+    let tile = harness.server_tile(item).unwrap();
+    let seen = harness.run_until(10, |harness| client_entity(harness, 0, item).is_some());
+    assert!(seen, "client never saw the potion");
+    let clicked = client_entity(&harness, 0, item).unwrap();
+    harness.send(
+        0,
+        PlayerCommand::LeftClick(LeftClick::Pickup(Some(clicked)), tile),
+    );
+
+    let picked_up = harness.run_until(20, |harness| {
+        client_inventory(harness, 0)
+            .is_some_and(|inventory| inventory.slots[0] == Some(ItemStack::new(potion, 3)))
+    });
+    assert!(picked_up, "client 0 never got the potions");
+    assert!(harness.server.world.get_entity(item).is_none());
+    let other = client_inventory(&mut harness, 1).unwrap_or_default();
+    assert!(other.slots.iter().all(Option::is_none));
+}
+
+#[test]
+fn items_out_of_reach_stay_on_the_ground() {
+    let mut harness = Harness::new(1);
+    let sword = harness
+        .server
+        .world
+        .resource::<Items>()
+        .id("sword")
+        .unwrap();
+    let item = harness.server_entities(EntityType::Item(sword))[0];
+    let player = harness.player(0).unwrap();
+    harness
+        .server
+        .world
+        .entity_mut(player)
+        .insert(Tile::new((10, 0, 10)));
+    let client_id = harness.client_id(0);
+    harness.server.world.send_event(InteractEvent {
+        client_id,
+        left_click: LeftClick::Pickup(Some(item)),
+    });
+    // clicks on nothing are ignored
+    harness.server.world.send_event(InteractEvent {
+        client_id,
+        left_click: LeftClick::Pickup(None),
+    });
+    harness.run(2);
+    assert!(harness.server.world.get_entity(item).is_some());
+    let inventory = harness.server.world.get::<Inventory>(player).unwrap();
+    assert!(inventory.slots.iter().all(Option::is_none));
+}
+
+#[test]
+fn a_stack_is_picked_up_only_once() {
+    let mut harness = Harness::new(2);
+    let potion = harness
+        .server
+        .world
+        .resource::<Items>()
+        .id("health potion")
+        .unwrap();
+    let item = harness.server_entities(EntityType::Item(potion))[0];
+    let tile = harness.server_tile(item).unwrap();
+    let mut players = vec![];
+    for client in 0..2 {
+        let player = harness.player(client).unwrap();
+        harness.server.world.entity_mut(player).insert(tile);
+        let client_id = harness.client_id(client);
+        harness.server.world.send_event(InteractEvent {
+            client_id,
+            left_click: LeftClick::Pickup(Some(item)),
+        });
+        players.push(player);
+    }
+    harness.run(2);
+    assert!(harness.server.world.get_entity(item).is_none());
+    let picked_up: u16 = players
+        .iter()
+        .flat_map(|player| {
+            harness
+                .server
+                .world
+                .get::<Inventory>(*player)
+                .unwrap()
+                .slots
+                .clone()
+        })
+        .flatten()
+        .map(|stack| stack.count)
+        .sum();
+    assert_eq!(picked_up, 3);
+}
+
+#[test]
+fn split_stacks_can_be_dropped() {
+    let mut harness = Harness::new(1);
+    let jelly = harness
+        .server
+        .world
+        .resource::<Items>()
+        .id("slime jelly")
+        .unwrap();
+    let player = harness.player(0).unwrap();
+    let mut inventory = Inventory::default();
+    inventory.slots[0] = Some(ItemStack::new(jelly, 12));
+    harness.server.world.entity_mut(player).insert(inventory);
+
+    harness.send(0, PlayerCommand::SplitStack(0, 5));
+    let split = harness.run_until(10, |harness| {
+        client_inventory(harness, 0).is_some_and(|inventory| inventory.slots[1].is_some())
+    });
+    assert!(split, "client never saw the split");
+    let inventory = client_inventory(&mut harness, 0).unwrap();
+    assert_eq!(inventory.slots[0], Some(ItemStack::new(jelly, 7)));
+    assert_eq!(inventory.slots[1], Some(ItemStack::new(jelly, 5)));
+
+    let on_ground = harness.server_entities(EntityType::Item(jelly)).len();
+    harness.send(0, PlayerCommand::DropItem(1));
+    let dropped = harness.run_until(10, |harness| {
+        harness.server_entities(EntityType::Item(jelly)).len() > on_ground
+    });
+    assert!(dropped, "the stack never hit the ground");
+    let inventory = harness.server.world.get::<Inventory>(player).unwrap();
+    assert_eq!(inventory.slots[1], None);
+}
+
+#[test]
+fn equipping_adds_stats_and_shows_to_others() {
+    let mut harness = Harness::new(2);
+    let sword = harness
+        .server
+        .world
+        .resource::<Items>()
+        .id("sword")
+        .unwrap();
+    let bonus = harness
+        .server
+        .world
+        .resource::<Items>()
+        .get(sword)
+        .unwrap()
+        .bonus;
+    let player = harness.player(0).unwrap();
+    let attack = harness.server.world.get::<Stats>(player).unwrap().attack;
+    let mut inventory = Inventory::default();
+    inventory.slots[0] = Some(ItemStack::new(sword, 1));
+    harness.server.world.entity_mut(player).insert(inventory);
+
+    harness.send(0, PlayerCommand::Equip(0));
+    let seen = harness.run_until(10, |harness| {
+        client_entity(harness, 1, player)
+            .and_then(|entity| harness.clients[1].world.get::<Equipment>(entity))
+            .is_some_and(|equipment| equipment.main_hand == Some(sword))
+    });
+    assert!(seen, "the other client never saw the sword");
+    let stats = harness.server.world.get::<Stats>(player).unwrap();
+    assert_eq!(stats.attack, attack + bonus.attack);
+    let inventory = harness.server.world.get::<Inventory>(player).unwrap();
+    assert!(inventory.slots.iter().all(Option::is_none));
+
+    harness.send(0, PlayerCommand::Unequip(EquipSlot::MainHand));
+    let unequipped = harness.run_until(10, |harness| {
+        harness.server.world.get::<Stats>(player).unwrap().attack == attack
+    });
+    assert!(unequipped, "the sword's bonus stayed");
+    let inventory = harness.server.world.get::<Inventory>(player).unwrap();
+    assert_eq!(inventory.slots[0], Some(ItemStack::new(sword, 1)));
+}
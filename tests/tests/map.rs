@@ -0,0 +1,81 @@
+use lib::{
+    components::{Arch, Door, EntityType, LeftClick, OpenState, Tile},
+    nav::NavGrid,
+};
+use server::{world::Map, InteractEvent};
+use tests::Harness;
+
+const TWO_FLOORS: &str = r#"(
+    floors: [
+        (y: 0, rows: [".....", ".....", ".D...", ".....", "....."]),
+        (y: 1, rows: ["...", ".S.", "..."]),
+    ],
+)"#;
+
+#[test]
+fn maps_load_every_floor_and_spawn_players_on_spawn_points() {
+    let mut harness = Harness::with_map(1, Map::from_ron(TWO_FLOORS).unwrap());
+    let player = harness.player(0).unwrap();
+    assert_eq!(harness.server_tile(player), Some(Tile::new((1, 1, 1))));
+
+    let floors = harness
+        .server_entities(EntityType::Tile)
+        .into_iter()
+        .filter_map(|tile| harness.server_tile(tile))
+        .fold([0, 0], |mut floors, tile| {
+            floors[tile.cell.1 as usize] += 1;
+            floors
+        });
+    assert_eq!(floors, [25, 9]);
+
+    let door = harness
+        .server
+        .world
+        .query::<(&EntityType, &OpenState)>()
+        .iter(&harness.server.world)
+        .find_map(|(entity_type, open_state)| match entity_type {
+            EntityType::Door(door) => Some((*door, *open_state)),
+            _ => None,
+        });
+    let (door, open_state) = door.expect("the map has no door");
+    assert_eq!(open_state, OpenState::Closed);
+    let grid = harness.server.world.resource::<NavGrid>();
+    assert!(!grid.is_walkable(&door.passage(Tile::new((2, 0, 1)))));
+    assert!(!grid.is_walkable(&Tile::new((2, 0, 3))));
+    assert!(grid.is_walkable(&Tile::new((1, 0, 2))));
+}
+
+#[test]
+fn broken_maps_are_rejected() {
+    let unknown = TWO_FLOORS.replace('S', "?");
+    assert!(Map::from_ron(&unknown).is_err());
+    let no_spawn = TWO_FLOORS.replace('S', ".");
+    assert!(Map::from_ron(&no_spawn)
+        .unwrap_err()
+        .contains("no spawn point"));
+    assert!(!Map::load().unwrap().spawners.is_empty());
+}
+
+#[test]
+fn only_doors_open() {
+    let mut harness = Harness::with_map(1, Map::from_ron(TWO_FLOORS).unwrap());
+    let client_id = harness.client_id(0);
+    let door = harness.server_entities(EntityType::Door(Door::Horizontal))[0];
+    let arch = harness.server_entities(EntityType::Arch(Arch::Horizontal))[0];
+    for left_click in [
+        LeftClick::Open(arch),
+        LeftClick::Open(door),
+        LeftClick::Pull,
+    ] {
+        harness.server.world.send_event(InteractEvent {
+            client_id,
+            left_click,
+        });
+    }
+    harness.run(2);
+    assert_eq!(
+        harness.server.world.get::<OpenState>(door),
+        Some(&OpenState::Open)
+    );
+    assert!(harness.server.world.get::<OpenState>(arch).is_none());
+}
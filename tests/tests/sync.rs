@@ -0,0 +1,197 @@
+use bevy::prelude::Entity;
+use bevy_renet::renet::RenetClient;
+use client::resources::NetworkMapping;
+use lib::{
+    channels::ClientChannel,
+    components::{Health, LeftClick, PlayerCommand, Tile},
+    net_id::NetIds,
+};
+use server::{resources::SnapshotHistory, world::Map};
+use tests::Harness;
+
+/// Nothing but the player, so what a test waits for isn't held up behind
+/// the updates of mobs.
+const EMPTY_ROOM: &str = r#"(floors: [(y: 0, rows: [".....", ".S...", "....."])])"#;
+
+fn empty_room() -> Harness {
+    Harness::with_map(1, Map::from_ron(EMPTY_ROOM).unwrap())
+}
+
+/// Spawns a crate on the server `x` tiles along from `client`'s player.
+fn spawn_crate(harness: &mut Harness, client: usize, x: u32) -> (Entity, Tile) {
+    let player = harness.player(client).unwrap();
+    let mut tile = harness.server_tile(player).unwrap();
+    tile.cell.0 += x;
+    let entity_type = harness.archetype("crate");
+    let entity = harness
+        .server
+        .world
+        .spawn((entity_type, tile, Health { hp: 10 }))
+        .id();
+    (entity, tile)
+}
+
+fn acked(harness: &Harness, client: usize) -> u64 {
+    let id = harness.client_id(client);
+    harness.server.world.resource::<SnapshotHistory>().clients[&id].acked
+}
+
+#[test]
+fn clients_see_each_other() {
+    let mut harness = Harness::new(2);
+    for client in 0..2 {
+        let player = harness.player(client).unwrap();
+        let tile = harness.server_tile(player).unwrap();
+        harness.assert_sees(0, player, tile, 10);
+        harness.assert_sees(1, player, tile, 10);
+    }
+}
+
+#[test]
+fn walking_is_replicated() {
+    let mut harness = Harness::new(2);
+    let player = harness.player(0).unwrap();
+    let destination = Tile::new((5, 0, 8));
+    harness.send(0, PlayerCommand::LeftClick(LeftClick::Walk, destination));
+    let arrived = harness.run_until(30, |harness| {
+        harness.server_tile(player) == Some(destination)
+    });
+    assert!(arrived, "player didn't reach {destination:?}");
+    harness.assert_sees(1, player, destination, 5);
+}
+
+#[test]
+fn disconnect_despawns_player() {
+    let mut harness = Harness::new(2);
+    let player = harness.player(1).unwrap();
+    let tile = harness.server_tile(player).unwrap();
+    harness.assert_sees(0, player, tile, 10);
+    let id = harness.net_id(player).unwrap();
+    harness.disconnect(1);
+    harness.assert_not_sees(0, id, 10);
+}
+
+#[test]
+fn malformed_commands_are_dropped() {
+    let mut harness = Harness::new(1);
+    harness.clients[0]
+        .world
+        .resource_mut::<RenetClient>()
+        .send_message(ClientChannel::Command, vec![0xff; 3]);
+    harness.run(2);
+    let player = harness.player(0).unwrap();
+    let destination = Tile::new((5, 0, 8));
+    harness.send(0, PlayerCommand::LeftClick(LeftClick::Walk, destination));
+    let arrived = harness.run_until(30, |harness| {
+        harness.server_tile(player) == Some(destination)
+    });
+    assert!(arrived, "the server stopped taking commands");
+}
+
+#[test]
+fn snapshots_are_deltas_of_the_last_acked_one() {
+    let mut harness = empty_room();
+    let player = harness.player(0).unwrap();
+    harness.run(5);
+    // the ack of the last step comes in on this one
+    harness.step_server();
+    let baseline = acked(&harness, 0);
+    assert!(baseline > 0, "the client never acked a snapshot");
+    harness.server.world.get_mut::<Health>(player).unwrap().hp = 1;
+    for _ in 0..3 {
+        harness.step_server();
+        let id = harness.client_id(0);
+        let history = harness.server.world.resource::<SnapshotHistory>();
+        let snapshots = &history.clients[&id];
+        assert_eq!(snapshots.acked, baseline);
+        assert_eq!(snapshots.baseline().map(|(tick, _)| *tick), Some(baseline));
+    }
+    let caught_up = harness.run_until(5, |harness| {
+        harness.client_component::<Health>(0, player) == Some(&Health { hp: 1 })
+    });
+    assert!(caught_up, "the client never decoded the delta");
+    harness.step_server();
+    assert!(acked(&harness, 0) > baseline);
+}
+
+#[test]
+fn updates_wait_for_the_spawn_of_their_entity() {
+    let mut harness = empty_room();
+    let (entity, tile) = spawn_crate(&mut harness, 0, 2);
+    harness.step_server();
+    harness.step_server();
+    let id = harness.net_id(entity).unwrap();
+    // the spawn and its first snapshot arrive in the same frame, the
+    // components are held back until the spawned entity exists
+    harness.step();
+    let world = &harness.clients[0].world;
+    assert!(world.resource::<NetIds>().entity(id).is_some());
+    assert!(world.resource::<NetworkMapping>().pending.contains_key(&id));
+    harness.step();
+    assert!(!harness.clients[0]
+        .world
+        .resource::<NetworkMapping>()
+        .pending
+        .contains_key(&id));
+    assert_eq!(harness.client_tile(0, entity), Some(tile));
+    assert_eq!(
+        harness.client_component::<Health>(0, entity),
+        Some(&Health { hp: 10 })
+    );
+
+    // a despawn and a respawn of the same tile held up together are
+    // applied in the order they were sent
+    harness.server.world.despawn(entity);
+    harness.step_server();
+    let (entity, tile) = spawn_crate(&mut harness, 0, 2);
+    harness.step_server();
+    harness.assert_not_sees(0, id, 5);
+    harness.assert_sees(0, entity, tile, 5);
+}
+
+#[test]
+fn net_ids_are_not_reused_after_a_despawn() {
+    let mut harness = empty_room();
+    let (old, tile) = spawn_crate(&mut harness, 0, 2);
+    harness.assert_sees(0, old, tile, 10);
+    let old_id = harness.net_id(old).unwrap();
+    harness.server.world.despawn(old);
+    // takes the index of the despawned one with a new generation
+    let (new, _) = spawn_crate(&mut harness, 0, 2);
+    assert_eq!(new.index(), old.index());
+    harness.assert_sees(0, new, tile, 10);
+    let new_id = harness.net_id(new).unwrap();
+    assert_ne!(new_id, old_id);
+    harness.assert_not_sees(0, old_id, 10);
+    assert_eq!(
+        harness.server.world.resource::<NetIds>().entity(old_id),
+        None
+    );
+}
+
+#[test]
+fn entities_enter_and_leave_scope() {
+    let mut harness = empty_room();
+    let (entity, tile) = spawn_crate(&mut harness, 0, 2);
+    harness.assert_sees(0, entity, tile, 10);
+    let id = harness.net_id(entity).unwrap();
+
+    // the crate moves out of the chunks the player sees and back
+    let mut far = tile;
+    far.cell.0 += 60;
+    *harness.server.world.get_mut::<Tile>(entity).unwrap() = far;
+    harness.assert_not_sees(0, id, 5);
+    *harness.server.world.get_mut::<Tile>(entity).unwrap() = tile;
+    harness.assert_sees(0, entity, tile, 5);
+
+    // the player moves away from the crate and back
+    let player = harness.player(0).unwrap();
+    let home = harness.server_tile(player).unwrap();
+    let mut away = home;
+    away.cell.2 += 60;
+    *harness.server.world.get_mut::<Tile>(player).unwrap() = away;
+    harness.assert_not_sees(0, id, 5);
+    *harness.server.world.get_mut::<Tile>(player).unwrap() = home;
+    harness.assert_sees(0, entity, tile, 5);
+    assert_eq!(harness.net_id(entity), Some(id));
+}